@@ -0,0 +1,404 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// The timing of one benchmark, normalized to nanoseconds per iteration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BenchResult {
+    pub name: String,
+    pub ns_per_iter: f64,
+    /// How far a measurement may stray from `ns_per_iter` by chance: libtest's `+/-` or half of
+    /// criterion's confidence interval.
+    pub noise_ns: Option<f64>,
+}
+
+/// Picks the timings out of `cargo bench` output. Understands the libtest bench harness
+/// (`test name ... bench: 1,234 ns/iter (+/- 56)`) and criterion (`name  time: [lo mid hi]`).
+pub fn parse_bench_output(lines: &[String]) -> Vec<BenchResult> {
+    static LIBTEST_REGEX: OnceLock<Regex> = OnceLock::new();
+    static CRITERION_REGEX: OnceLock<Regex> = OnceLock::new();
+    let libtest_regex = LIBTEST_REGEX.get_or_init(|| {
+        Regex::new(r"^test (\S+)\s+\.\.\. bench:\s+([\d,.]+) ns/iter \(\+/- ([\d,.]+)\)").unwrap()
+    });
+    let criterion_regex = CRITERION_REGEX.get_or_init(|| {
+        Regex::new(r"^(.*?)\s*time:\s+\[(\S+) (\S+) (\S+) (\S+) (\S+) (\S+)\]").unwrap()
+    });
+
+    let mut results = Vec::new();
+    // Criterion puts names that don't fit its column on a line of their own.
+    let mut pending_name: Option<&str> = None;
+    for line in lines {
+        if let Some(captures) = libtest_regex.captures(line) {
+            let (Some(ns), Some(noise)) = (parse_number(&captures[2]), parse_number(&captures[3]))
+            else {
+                continue;
+            };
+            results.push(BenchResult {
+                name: captures[1].to_string(),
+                ns_per_iter: ns,
+                noise_ns: Some(noise),
+            });
+        } else if let Some(captures) = criterion_regex.captures(line) {
+            let name = match captures[1].trim() {
+                "" => pending_name.take().unwrap_or_default(),
+                name => name,
+            };
+            let low = criterion_ns(&captures[2], &captures[3]);
+            let mid = criterion_ns(&captures[4], &captures[5]);
+            let high = criterion_ns(&captures[6], &captures[7]);
+            if let (false, Some(mid)) = (name.is_empty(), mid) {
+                results.push(BenchResult {
+                    name: name.to_string(),
+                    ns_per_iter: mid,
+                    noise_ns: low.zip(high).map(|(low, high)| (high - low) / 2.0),
+                });
+            }
+            pending_name = None;
+        } else {
+            let trimmed = line.trim();
+            if !trimmed.is_empty()
+                && !trimmed.starts_with("Benchmarking ")
+                && !line.starts_with(' ')
+            {
+                pending_name = Some(trimmed);
+            }
+        }
+    }
+    results
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    text.replace(',', "").parse().ok()
+}
+
+fn criterion_ns(value: &str, unit: &str) -> Option<f64> {
+    let scale = match unit {
+        "ps" => 1e-3,
+        "ns" => 1.0,
+        "µs" | "us" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        _ => return None,
+    };
+    Some(parse_number(value)? * scale)
+}
+
+/// A named set of results kept to compare later runs against.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Baseline {
+    pub name: String,
+    /// Seconds since the Unix epoch.
+    pub saved_at: u64,
+    pub results: Vec<BenchResult>,
+}
+
+/// Baselines of one project, one JSON file each.
+pub struct BaselineStore {
+    dir: PathBuf,
+}
+
+impl BaselineStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn save(&self, name: &str, results: &[BenchResult]) -> Result<Baseline> {
+        let baseline = Baseline {
+            name: name.to_string(),
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            results: results.to_vec(),
+        };
+        let path = self.path(name)?;
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        std::fs::write(&path, serde_json::to_vec_pretty(&baseline)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(baseline)
+    }
+
+    pub fn load(&self, name: &str) -> Result<Baseline> {
+        let path = self.path(name)?;
+        let content = match std::fs::read(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let names = self.names();
+                if names.is_empty() {
+                    bail!("No baseline named '{}'. None have been saved yet.", name);
+                }
+                bail!(
+                    "No baseline named '{}'. Saved baselines: {}",
+                    name,
+                    names.join(", ")
+                );
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Fails unless `name` can be used as a baseline's file name.
+    pub fn validate_name(name: &str) -> Result<()> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !name.starts_with('.');
+        if !valid {
+            bail!(
+                "Invalid baseline name '{}': use letters, digits, '-', '_' and '.'",
+                name
+            );
+        }
+        Ok(())
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        Self::validate_name(name)?;
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Improved,
+    Regressed,
+    /// The change is below the threshold or within the measurements' noise.
+    Unchanged,
+    /// Not in the baseline.
+    New,
+    /// In the baseline but not in this run.
+    Missing,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchComparison {
+    pub name: String,
+    pub baseline_ns: Option<f64>,
+    pub current_ns: Option<f64>,
+    /// Positive when the benchmark got slower.
+    pub change_percent: Option<f64>,
+    pub verdict: Verdict,
+}
+
+/// Compares a run against a baseline. A change counts only if it exceeds `threshold_percent`
+/// and the combined noise of both measurements, so jitter isn't reported as a regression.
+pub fn compare(
+    baseline: &[BenchResult],
+    current: &[BenchResult],
+    threshold_percent: f64,
+) -> Vec<BenchComparison> {
+    let mut comparisons: Vec<BenchComparison> = current
+        .iter()
+        .map(|result| {
+            let Some(before) = baseline.iter().find(|before| before.name == result.name) else {
+                return BenchComparison {
+                    name: result.name.clone(),
+                    baseline_ns: None,
+                    current_ns: Some(result.ns_per_iter),
+                    change_percent: None,
+                    verdict: Verdict::New,
+                };
+            };
+            let delta = result.ns_per_iter - before.ns_per_iter;
+            let change_percent = if before.ns_per_iter > 0.0 {
+                delta / before.ns_per_iter * 100.0
+            } else {
+                0.0
+            };
+            let noise = before.noise_ns.unwrap_or(0.0) + result.noise_ns.unwrap_or(0.0);
+            let verdict = if change_percent.abs() <= threshold_percent || delta.abs() <= noise {
+                Verdict::Unchanged
+            } else if delta > 0.0 {
+                Verdict::Regressed
+            } else {
+                Verdict::Improved
+            };
+            BenchComparison {
+                name: result.name.clone(),
+                baseline_ns: Some(before.ns_per_iter),
+                current_ns: Some(result.ns_per_iter),
+                change_percent: Some((change_percent * 100.0).round() / 100.0),
+                verdict,
+            }
+        })
+        .collect();
+
+    comparisons.extend(
+        baseline
+            .iter()
+            .filter(|before| !current.iter().any(|result| result.name == before.name))
+            .map(|before| BenchComparison {
+                name: before.name.clone(),
+                baseline_ns: Some(before.ns_per_iter),
+                current_ns: None,
+                change_percent: None,
+                verdict: Verdict::Missing,
+            }),
+    );
+    comparisons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(output: &str) -> Vec<String> {
+        output.lines().map(str::to_string).collect()
+    }
+
+    fn result(name: &str, ns_per_iter: f64, noise_ns: Option<f64>) -> BenchResult {
+        BenchResult {
+            name: name.to_string(),
+            ns_per_iter,
+            noise_ns,
+        }
+    }
+
+    fn verdicts(comparisons: &[BenchComparison]) -> Vec<(&str, Verdict)> {
+        comparisons
+            .iter()
+            .map(|comparison| (comparison.name.as_str(), comparison.verdict))
+            .collect()
+    }
+
+    #[test]
+    fn parses_libtest_output() {
+        let output = lines(
+            "
+running 3 tests
+test parser::tests::parse_empty ... ignored
+test bench_parse_large     ... bench:       1,234.56 ns/iter (+/- 56.78)
+test tests::bench_small    ... bench:          12 ns/iter (+/- 1)
+
+test result: ok. 0 passed; 0 failed; 1 ignored; 2 measured; 0 filtered out; finished in 2.41s
+",
+        );
+        let results = parse_bench_output(&output);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "bench_parse_large");
+        assert_eq!(results[0].ns_per_iter, 1234.56);
+        assert_eq!(results[0].noise_ns, Some(56.78));
+        assert_eq!(results[1].name, "tests::bench_small");
+        assert_eq!(results[1].ns_per_iter, 12.0);
+        assert_eq!(results[1].noise_ns, Some(1.0));
+    }
+
+    #[test]
+    fn parses_criterion_output() {
+        let output = lines(
+            "
+Benchmarking fib 20
+Benchmarking fib 20: Warming up for 3.0000 s
+Benchmarking fib 20: Collecting 100 samples in estimated 5.0371 s (278k iterations)
+Benchmarking fib 20: Analyzing
+fib 20                  time:   [18.116 µs 18.181 µs 18.254 µs]
+                        change: [-1.2329% -0.5082% +0.2183%] (p = 0.17 > 0.05)
+                        No change in performance detected.
+Found 8 outliers among 100 measurements (8.00%)
+  5 (5.00%) high mild
+  3 (3.00%) high severe
+Benchmarking decode/large_input_with_long_name/4096
+Benchmarking decode/large_input_with_long_name/4096: Warming up for 3.0000 s
+Benchmarking decode/large_input_with_long_name/4096: Analyzing
+decode/large_input_with_long_name/4096
+                        time:   [1.2034 ms 1.2100 ms 1.2178 ms]
+                        thrpt:  [3.3586 MiB/s 3.3803 MiB/s 3.3989 MiB/s]
+",
+        );
+        let results = parse_bench_output(&output);
+        assert_eq!(results.len(), 2);
+
+        assert_eq!(results[0].name, "fib 20");
+        assert!((results[0].ns_per_iter - 18_181.0).abs() < 1e-6);
+        let noise = results[0].noise_ns.unwrap();
+        assert!((noise - (18_254.0 - 18_116.0) / 2.0).abs() < 1e-6);
+
+        assert_eq!(results[1].name, "decode/large_input_with_long_name/4096");
+        assert!((results[1].ns_per_iter - 1_210_000.0).abs() < 1e-3);
+        let noise = results[1].noise_ns.unwrap();
+        assert!((noise - (1_217_800.0 - 1_203_400.0) / 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn change_up_to_the_threshold_is_unchanged() {
+        let baseline = [result("at", 100.0, None), result("above", 100.0, None)];
+        let current = [result("at", 105.0, None), result("above", 105.5, None)];
+        let comparisons = compare(&baseline, &current, 5.0);
+        assert_eq!(
+            verdicts(&comparisons),
+            [("at", Verdict::Unchanged), ("above", Verdict::Regressed)]
+        );
+        assert_eq!(comparisons[1].change_percent, Some(5.5));
+    }
+
+    #[test]
+    fn change_within_the_combined_noise_is_unchanged() {
+        let baseline = [
+            result("within", 100.0, Some(4.0)),
+            result("slower", 100.0, Some(4.0)),
+            result("faster", 100.0, Some(4.0)),
+        ];
+        let current = [
+            result("within", 110.0, Some(6.0)),
+            result("slower", 110.5, Some(6.0)),
+            result("faster", 89.5, Some(6.0)),
+        ];
+        let comparisons = compare(&baseline, &current, 5.0);
+        assert_eq!(
+            verdicts(&comparisons),
+            [
+                ("within", Verdict::Unchanged),
+                ("slower", Verdict::Regressed),
+                ("faster", Verdict::Improved),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_new_and_missing_benchmarks() {
+        let baseline = [result("kept", 100.0, None), result("removed", 50.0, None)];
+        let current = [result("kept", 100.0, None), result("added", 10.0, None)];
+        let comparisons = compare(&baseline, &current, 5.0);
+        assert_eq!(
+            verdicts(&comparisons),
+            [
+                ("kept", Verdict::Unchanged),
+                ("added", Verdict::New),
+                ("removed", Verdict::Missing),
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_baseline_names() {
+        assert!(BaselineStore::validate_name("before-refactor_2.1").is_ok());
+        for name in ["", ".hidden", "../escape", "a/b", "with space"] {
+            assert!(BaselineStore::validate_name(name).is_err(), "{}", name);
+        }
+    }
+}
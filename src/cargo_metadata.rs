@@ -0,0 +1,355 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// The subset of `cargo metadata --format-version 1` output the tools use.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Metadata {
+    pub packages: Vec<Package>,
+    pub workspace_members: Vec<String>,
+    #[serde(default)]
+    pub workspace_default_members: Vec<String>,
+    pub resolve: Option<Resolve>,
+    pub workspace_root: PathBuf,
+    pub target_directory: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub id: String,
+    /// `None` for path dependencies and workspace members.
+    pub source: Option<String>,
+    pub manifest_path: PathBuf,
+    pub edition: String,
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+    #[serde(default)]
+    pub targets: Vec<Target>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Dependency {
+    pub name: String,
+    pub req: String,
+    /// `None` for normal dependencies, otherwise `"dev"` or `"build"`.
+    pub kind: Option<String>,
+    pub rename: Option<String>,
+    pub optional: bool,
+    pub uses_default_features: bool,
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Platform cfg or triple for target-specific dependencies.
+    pub target: Option<String>,
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Target {
+    pub name: String,
+    pub kind: Vec<String>,
+    #[serde(default)]
+    pub crate_types: Vec<String>,
+    pub src_path: PathBuf,
+    #[serde(default)]
+    pub required_features: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Resolve {
+    pub nodes: Vec<Node>,
+    pub root: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Node {
+    pub id: String,
+    #[serde(default)]
+    pub deps: Vec<NodeDep>,
+    /// Features enabled for this package in the resolved graph.
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NodeDep {
+    pub name: String,
+    pub pkg: String,
+    #[serde(default)]
+    pub dep_kinds: Vec<DepKindInfo>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DepKindInfo {
+    pub kind: Option<String>,
+    pub target: Option<String>,
+}
+
+/// Kind of a dependency edge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    Normal,
+    Dev,
+    Build,
+}
+
+impl DependencyKind {
+    pub const ALL: [DependencyKind; 3] = [Self::Normal, Self::Dev, Self::Build];
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "normal" => Some(Self::Normal),
+            "dev" => Some(Self::Dev),
+            "build" => Some(Self::Build),
+            _ => None,
+        }
+    }
+
+    fn from_metadata(kind: Option<&str>) -> Self {
+        match kind {
+            Some("dev") => Self::Dev,
+            Some("build") => Self::Build,
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// Which edges of the resolved graph to walk and how far.
+#[derive(Clone, Debug)]
+pub struct TreeOptions {
+    pub kinds: Vec<DependencyKind>,
+    pub max_depth: Option<usize>,
+    /// Walk from a package to the packages that depend on it instead of to its dependencies.
+    pub invert: bool,
+}
+
+/// A package in a rendered dependency tree, like one line of `cargo tree`.
+#[derive(Clone, Debug, Serialize)]
+pub struct DependencyNode {
+    pub name: String,
+    pub version: String,
+    /// Kinds of the edge from the parent. Empty for the root.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<DependencyKind>,
+    /// The package was already expanded earlier in the tree, so its children are omitted.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub repeated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<DependencyNode>,
+}
+
+impl DependencyNode {
+    /// Renders the tree with `cargo tree` style guides. Repeated packages are marked `(*)`.
+    pub fn render(&self) -> String {
+        let mut out = self.label();
+        out.push('\n');
+        self.render_children("", &mut out);
+        out
+    }
+
+    fn render_children(&self, prefix: &str, out: &mut String) {
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i + 1 == self.children.len();
+            out.push_str(prefix);
+            out.push_str(if last { "└── " } else { "├── " });
+            out.push_str(&child.label());
+            out.push('\n');
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            child.render_children(&prefix, out);
+        }
+    }
+
+    fn label(&self) -> String {
+        let mut label = format!("{} v{}", self.name, self.version);
+        let kinds: Vec<&str> = self
+            .kinds
+            .iter()
+            .filter_map(|kind| match kind {
+                DependencyKind::Normal => None,
+                DependencyKind::Dev => Some("dev"),
+                DependencyKind::Build => Some("build"),
+            })
+            .collect();
+        if !kinds.is_empty() && !self.kinds.contains(&DependencyKind::Normal) {
+            label.push_str(&format!(" ({})", kinds.join(", ")));
+        }
+        if self.repeated {
+            label.push_str(" (*)");
+        }
+        label
+    }
+}
+
+type Edges<'a> = HashMap<&'a str, Vec<(&'a str, Vec<DependencyKind>)>>;
+
+/// The resolved graph as `dependency_tree` walks it, with packages looked up by id.
+struct Graph<'a> {
+    edges: Edges<'a>,
+    packages: HashMap<&'a str, &'a Package>,
+}
+
+impl Metadata {
+    pub fn package_by_id(&self, id: &str) -> Option<&Package> {
+        self.packages.iter().find(|package| package.id == id)
+    }
+
+    pub fn workspace_packages(&self) -> impl Iterator<Item = &Package> {
+        self.workspace_members
+            .iter()
+            .filter_map(|id| self.package_by_id(id))
+    }
+
+    /// Finds a workspace member by name, falling back to any package in the graph.
+    pub fn package_by_name(&self, name: &str) -> Option<&Package> {
+        self.workspace_packages()
+            .find(|package| package.name == name)
+            .or_else(|| self.packages.iter().find(|package| package.name == name))
+    }
+
+    /// The package whose directory most closely contains `file`.
+    pub fn package_for_file(&self, file: &Path) -> Option<&Package> {
+        self.packages
+            .iter()
+            .filter(|package| file.starts_with(package.root()))
+            .max_by_key(|package| package.root().components().count())
+    }
+
+    /// Packages that appear in the resolved graph, i.e. are actually built for this workspace.
+    pub fn resolved_packages(&self) -> impl Iterator<Item = &Package> {
+        self.resolve
+            .iter()
+            .flat_map(|resolve| &resolve.nodes)
+            .filter_map(|node| self.package_by_id(&node.id))
+    }
+
+    /// Packages resolved in more than one version, grouped by name and sorted by version.
+    pub fn duplicate_packages(&self) -> Vec<Vec<&Package>> {
+        let mut by_name: BTreeMap<&str, Vec<&Package>> = BTreeMap::new();
+        for package in self.resolved_packages() {
+            by_name.entry(&package.name).or_default().push(package);
+        }
+        by_name
+            .into_values()
+            .filter(|packages| packages.len() > 1)
+            .map(|mut packages| {
+                packages.sort_by_key(|package| version_key(&package.version));
+                packages
+            })
+            .collect()
+    }
+
+    /// Walks the resolved graph from `root_id`. Each package is expanded once; later occurrences
+    /// are marked as repeated, which also guards against cycles through dev-dependencies.
+    pub fn dependency_tree(&self, root_id: &str, options: &TreeOptions) -> Option<DependencyNode> {
+        let graph = Graph {
+            edges: self.edges(options),
+            packages: self
+                .packages
+                .iter()
+                .map(|package| (package.id.as_str(), package))
+                .collect(),
+        };
+        let mut visited = HashSet::new();
+        Self::walk(root_id, Vec::new(), 0, &graph, options, &mut visited)
+    }
+
+    fn edges(&self, options: &TreeOptions) -> Edges<'_> {
+        let mut edges: Edges<'_> = HashMap::new();
+        for node in self.resolve.iter().flat_map(|resolve| &resolve.nodes) {
+            for dep in &node.deps {
+                let mut kinds: Vec<DependencyKind> = if dep.dep_kinds.is_empty() {
+                    vec![DependencyKind::Normal]
+                } else {
+                    dep.dep_kinds
+                        .iter()
+                        .map(|info| DependencyKind::from_metadata(info.kind.as_deref()))
+                        .collect()
+                };
+                kinds.retain(|kind| options.kinds.contains(kind));
+                kinds.sort();
+                kinds.dedup();
+                if kinds.is_empty() {
+                    continue;
+                }
+                let (from, to) = if options.invert {
+                    (dep.pkg.as_str(), node.id.as_str())
+                } else {
+                    (node.id.as_str(), dep.pkg.as_str())
+                };
+                edges.entry(from).or_default().push((to, kinds));
+            }
+        }
+        edges
+    }
+
+    fn walk<'a>(
+        id: &'a str,
+        kinds: Vec<DependencyKind>,
+        depth: usize,
+        graph: &Graph<'a>,
+        options: &TreeOptions,
+        visited: &mut HashSet<&'a str>,
+    ) -> Option<DependencyNode> {
+        let package = graph.packages.get(id)?;
+        let repeated = !visited.insert(id);
+        let mut children = Vec::new();
+        if !repeated && options.max_depth.is_none_or(|max| depth < max) {
+            let mut next: Vec<&(&str, Vec<DependencyKind>)> = graph
+                .edges
+                .get(id)
+                .map(|e| e.iter().collect())
+                .unwrap_or_default();
+            next.sort_by_key(|(id, _)| {
+                graph
+                    .packages
+                    .get(id)
+                    .map(|package| (package.name.as_str(), version_key(&package.version)))
+            });
+            for (child, kinds) in next {
+                if let Some(node) =
+                    Self::walk(child, kinds.clone(), depth + 1, graph, options, visited)
+                {
+                    children.push(node);
+                }
+            }
+        }
+        Some(DependencyNode {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            kinds,
+            repeated,
+            children,
+        })
+    }
+
+    /// The resolved node for a package, carrying its enabled features and resolved dependencies.
+    pub fn resolved_node(&self, id: &str) -> Option<&Node> {
+        self.resolve
+            .as_ref()?
+            .nodes
+            .iter()
+            .find(|node| node.id == id)
+    }
+}
+
+impl Package {
+    pub fn root(&self) -> &Path {
+        self.manifest_path.parent().unwrap_or(&self.manifest_path)
+    }
+}
+
+/// Sort key that orders `major.minor.patch` numerically; pre-release and build suffixes are ignored.
+pub fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
@@ -1,25 +1,73 @@
-use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Result, anyhow, bail};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json as json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
+use tokio::sync::watch;
+use tokio::task::AbortHandle;
 
+use crate::bench::{BaselineStore, BenchResult, parse_bench_output};
+use crate::cargo_metadata::{Metadata, Package, version_key};
+use crate::crate_docs::CrateDocs;
 use crate::project::Project;
 
+/// How much of a run program's stdout and stderr is kept, each.
+const RUN_OUTPUT_LIMIT: usize = 32 * 1024;
+/// How many trailing lines of stderr and of the harness output a failed bench run keeps.
+const BENCH_FAILURE_LINES: usize = 40;
+/// How many crates' documentation stays loaded. The least recently used is dropped beyond this.
+const MAX_LOADED_CRATE_DOCS: usize = 16;
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "reason", rename_all = "kebab-case")]
 pub enum CargoMessage {
     CompilerArtifact(json::Value),
     BuildScriptExecuted(json::Value),
-    CompilerMessage { message: CompilerMessage },
-    BuildFinished { success: bool },
+    CompilerMessage {
+        message: CompilerMessage,
+        #[serde(default)]
+        target: Option<MessageTarget>,
+    },
+    BuildFinished {
+        success: bool,
+    },
+}
+
+/// The target a compiler message was emitted for.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MessageTarget {
+    pub name: String,
+    pub src_path: PathBuf,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub struct CompilerMessage {
+    pub message: String,
     pub rendered: String,
-    pub code: Option<json::Value>,
+    pub code: Option<DiagnosticCode>,
+    pub level: String,
+    pub spans: Vec<CompilerMessageSpan>,
+    /// Attached `note:`, `help:` and suggestion sub-diagnostics.
+    #[serde(default)]
+    pub children: Vec<CompilerMessageChild>,
+}
+
+/// A sub-diagnostic. rustc never nests them further and doesn't render them separately.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CompilerMessageChild {
+    pub message: String,
     pub level: String,
+    #[serde(default)]
     pub spans: Vec<CompilerMessageSpan>,
 }
 
@@ -33,36 +81,587 @@ pub struct CompilerMessageSpan {
     pub line_end: usize,
     // This field is crucial for identifying the main source of an error.
     pub is_primary: bool,
+    /// Source lines the span covers.
+    #[serde(default)]
+    pub text: Vec<CompilerMessageSpanLine>,
+    /// What the span is annotated with, e.g. "first mutable borrow occurs here".
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Replacement text when the span belongs to a suggestion.
+    #[serde(default)]
+    pub suggested_replacement: Option<String>,
+    #[serde(default)]
+    pub suggestion_applicability: Option<String>,
+    /// Set when the span lies in code produced by a macro.
+    #[serde(default)]
+    pub expansion: Option<Box<SpanExpansion>>,
+}
+
+/// The macro invocation a span was expanded from.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SpanExpansion {
+    /// The invocation site, itself possibly inside another expansion.
+    pub span: CompilerMessageSpan,
+    /// e.g. `vec!` or `#[derive(Debug)]`.
+    pub macro_decl_name: String,
+    #[serde(default)]
+    pub def_site_span: Option<CompilerMessageSpan>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CompilerMessageSpanLine {
+    pub text: String,
+    pub highlight_start: usize,
+    pub highlight_end: usize,
+}
+
+/// The lint or error code of a diagnostic, e.g. `clippy::needless_borrow` or `E0502`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DiagnosticCode {
+    pub code: String,
+    /// rustc's long-form explanation, only present for error codes.
+    pub explanation: Option<String>,
+}
+
+impl DiagnosticCode {
+    /// Whether this is a compiler error code like `E0502`, which `rustc --explain` knows.
+    pub fn is_error_code(&self) -> bool {
+        normalize_error_code(&self.code).is_some()
+    }
+}
+
+impl CompilerMessage {
+    /// The lint or error code attached to this diagnostic, e.g. `clippy::needless_borrow` or `E0502`.
+    pub fn code_name(&self) -> Option<&str> {
+        self.code.as_ref().map(|code| code.code.as_str())
+    }
+
+    /// A diagnostic for a failure outside of rustc (a build script, the manifest, ...), located at
+    /// the start of `file_name` so it can be handled like any other.
+    fn cargo(level: &str, message: String, rendered: String, file_name: String) -> Self {
+        Self {
+            message,
+            rendered,
+            code: None,
+            level: level.to_string(),
+            spans: vec![CompilerMessageSpan {
+                column_start: 1,
+                column_end: 1,
+                file_name,
+                line_start: 1,
+                line_end: 1,
+                is_primary: true,
+                text: Vec::new(),
+                label: None,
+                suggested_replacement: None,
+                suggestion_applicability: None,
+                expansion: None,
+            }],
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Diagnostics of a `cargo check` or `cargo clippy` run.
+#[derive(Clone, Debug)]
+pub struct BuildReport {
+    pub diagnostics: Vec<CompilerMessage>,
+    /// Whether cargo finished the build. A failed build always has at least one error diagnostic.
+    pub success: bool,
+}
+
+/// `E0502` for `E0502`, `e0502` or `0502`. `None` for anything that isn't an error code.
+pub fn normalize_error_code(code: &str) -> Option<String> {
+    let code = code.trim();
+    let digits = code
+        .strip_prefix('E')
+        .or_else(|| code.strip_prefix('e'))
+        .unwrap_or(code);
+    if digits.len() == 4 && digits.chars().all(|c| c.is_ascii_digit()) {
+        Some(format!("E{}", digits))
+    } else {
+        None
+    }
+}
+
+/// The prose at the start of an error explanation, before its first code example.
+pub fn explanation_excerpt(explanation: &str) -> String {
+    let prose = explanation
+        .split("```")
+        .next()
+        .unwrap_or_default()
+        .trim_end();
+    // Drop the "Erroneous code example:" line introducing the example.
+    match prose.rsplit_once('\n') {
+        Some((before, last)) if last.trim_end().ends_with(':') => before.trim().to_string(),
+        _ => prose.trim().to_string(),
+    }
+}
+
+/// Per-call lint level overrides passed through to `cargo clippy`.
+/// Entries can be lint names (`clippy::needless_borrow`) or lint groups (`clippy::pedantic`).
+#[derive(Clone, Debug, Default)]
+pub struct LintOverrides {
+    pub allow: Vec<String>,
+    pub warn: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl LintOverrides {
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (flag, lints) in [("-A", &self.allow), ("-W", &self.warn), ("-D", &self.deny)] {
+            for lint in lints {
+                args.push(flag.to_string());
+                args.push(lint.clone());
+            }
+        }
+        args
+    }
+}
+
+/// What a rustfmt run should cover.
+#[derive(Clone, Debug)]
+pub enum FormatTarget {
+    Project,
+    Package(String),
+    Files(Vec<PathBuf>),
+}
+
+/// Result of a rustfmt run. `diff` holds the changes rustfmt made (or would make).
+#[derive(Clone, Debug, Serialize)]
+pub struct FormatOutcome {
+    pub is_formatted: bool,
+    pub applied: bool,
+    pub diff: String,
+}
+
+/// What `CargoRemote::run` builds and executes.
+#[derive(Clone, Debug)]
+pub enum RunTarget {
+    /// A binary target. Without a name, the only binary there is.
+    Bin(Option<String>),
+    Example(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct RunOptions {
+    pub package: Option<String>,
+    pub release: bool,
+    pub args: Vec<String>,
+    /// Set on top of the project's configured `env`.
+    pub env: Vec<(String, String)>,
+    pub stdin: Option<String>,
+    /// Working directory relative to the project root, which is the default.
+    pub cwd: Option<PathBuf>,
+    /// Wall-clock limit for the program itself, not counting the build.
+    pub timeout: Duration,
+}
+
+/// How a program run ended. Output is cut to its beginning and end past `RUN_OUTPUT_LIMIT`.
+#[derive(Clone, Debug, Serialize)]
+pub struct RunOutcome {
+    pub executable: PathBuf,
+    /// `None` if the process was killed by a signal, including on timeout.
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u128,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub enum RunResult {
+    BuildFailed(BuildReport),
+    Finished(RunOutcome),
+}
+
+pub enum BenchOutcome {
+    /// The benches didn't build, or one of them panicked. A panic message only shows up in the
+    /// end of `stderr`, and which benchmark failed in the end of the harness `output`.
+    Failed {
+        report: BuildReport,
+        stderr: String,
+        output: String,
+    },
+    Finished(Vec<BenchResult>),
+}
+
+/// A snapshot of one queued or running cargo invocation, as shown to clients.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub command: String,
+    pub state: JobState,
+    /// Number of tool calls waiting for this job's result.
+    pub waiters: usize,
+    /// Seconds spent in the current state.
+    pub elapsed_secs: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
 }
 
 #[derive(Clone, Debug)]
 pub struct CargoRemote {
     repository: Project,
+    jobs: Arc<JobQueue>,
+    metadata: Arc<MetadataCache>,
+    /// `rustc --explain` output per error code. Explanations only change with the toolchain.
+    explanations: Arc<Mutex<BTreeMap<String, Arc<str>>>>,
+    /// Loaded crate documentation per package id, at most `MAX_LOADED_CRATE_DOCS` of them.
+    crate_docs: Arc<Mutex<HashMap<String, LoadedDocs>>>,
 }
 
 impl CargoRemote {
     pub fn new(repository: Project) -> Self {
-        Self { repository }
+        Self {
+            repository,
+            jobs: Arc::new(JobQueue::default()),
+            metadata: Arc::new(MetadataCache::default()),
+            explanations: Arc::default(),
+            crate_docs: Arc::default(),
+        }
     }
 
-    async fn run_cargo_command(
+    /// Workspace metadata from `cargo metadata`, cached until `invalidate_metadata` is called.
+    /// Runs outside the job queue: it never touches the target dir and shouldn't wait behind a
+    /// long test run.
+    pub async fn metadata(&self) -> Result<Arc<Metadata>> {
+        if let Some(metadata) = self.metadata.cached.lock().unwrap().clone() {
+            return Ok(metadata);
+        }
+        let _fetching = self.metadata.fetch.lock().await;
+        if let Some(metadata) = self.metadata.cached.lock().unwrap().clone() {
+            return Ok(metadata);
+        }
+
+        let generation = self.metadata.generation.load(Ordering::SeqCst);
+        let root = self.repository.root();
+        let output = output_killable(
+            Command::new("cargo")
+                .current_dir(root)
+                .args(["metadata", "--format-version", "1", "--offline"])
+                .envs(self.repository.settings().cargo.env(root)),
+        )
+        .await?;
+        if !output.status.success() {
+            bail!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let metadata = Arc::new(json::from_slice::<Metadata>(&output.stdout)?);
+
+        let mut cached = self.metadata.cached.lock().unwrap();
+        // A manifest edited while we were running makes this result stale already.
+        if self.metadata.generation.load(Ordering::SeqCst) == generation {
+            *cached = Some(metadata.clone());
+        }
+        Ok(metadata)
+    }
+
+    /// Long-form explanation of a compiler error code from `rustc --explain`, run with the
+    /// project's toolchain. Runs outside the job queue and is cached.
+    pub async fn explain(&self, code: &str) -> Result<Arc<str>> {
+        let Some(code) = normalize_error_code(code) else {
+            bail!("'{}' is not a compiler error code like E0502", code);
+        };
+        if let Some(explanation) = self.explanations.lock().unwrap().get(&code) {
+            return Ok(explanation.clone());
+        }
+
+        let root = self.repository.root();
+        let output = output_killable(
+            Command::new("rustc")
+                .current_dir(root)
+                .args(["--explain", &code])
+                .envs(self.repository.settings().cargo.env(root)),
+        )
+        .await?;
+        if !output.status.success() {
+            bail!(
+                "rustc --explain {} failed: {}",
+                code,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let explanation: Arc<str> = String::from_utf8_lossy(&output.stdout).trim().into();
+        self.explanations
+            .lock()
+            .unwrap()
+            .insert(code, explanation.clone());
+        Ok(explanation)
+    }
+
+    /// Documentation of the library crate `crate_name`, from the workspace or its dependencies,
+    /// built offline from the sources cargo already has. Docs of registry packages are kept on
+    /// disk per version; those of path packages are rebuilt once their sources change.
+    pub async fn crate_docs(&self, crate_name: &str) -> Result<(Package, Arc<CrateDocs>)> {
+        let metadata = self.metadata().await?;
+        let crate_name = crate_name.replace('-', "_");
+        let package = metadata
+            .packages
+            .iter()
+            .filter(|package| {
+                package.targets.iter().any(|target| {
+                    target.name.replace('-', "_") == crate_name
+                        && target
+                            .kind
+                            .iter()
+                            .any(|kind| kind.ends_with("lib") || kind == "proc-macro")
+                })
+            })
+            .max_by_key(|package| version_key(&package.version))
+            .ok_or_else(|| {
+                anyhow!(
+                    "'{}' is not a library of the workspace or its dependencies",
+                    crate_name
+                )
+            })?
+            .clone();
+
+        let changed = match package.source {
+            Some(_) => None,
+            None => {
+                let root = package.root().to_path_buf();
+                tokio::task::spawn_blocking(move || newest_source_modification(&root)).await?
+            }
+        };
+        if let Some(loaded) = self.crate_docs.lock().unwrap().get_mut(&package.id)
+            && changed.is_none_or(|changed| changed <= loaded.built)
+        {
+            loaded.used = Instant::now();
+            return Ok((package, loaded.docs.clone()));
+        }
+
+        let built = SystemTime::now();
+        let cached = metadata
+            .target_directory
+            .join("crate-docs")
+            .join(format!("{}-{}.json", package.name, package.version));
+        let docs = if package.source.is_some() && cached.is_file() {
+            CrateDocs::load_json(&cached, metadata.workspace_root.clone())?
+        } else {
+            self.build_crate_docs(&package, &crate_name, &cached, &metadata)
+                .await?
+        };
+        let docs = Arc::new(docs);
+        let mut loaded = self.crate_docs.lock().unwrap();
+        loaded.insert(
+            package.id.clone(),
+            LoadedDocs {
+                docs: docs.clone(),
+                built,
+                used: Instant::now(),
+            },
+        );
+        if loaded.len() > MAX_LOADED_CRATE_DOCS
+            && let Some(least_used) = loaded
+                .iter()
+                .min_by_key(|(_, docs)| docs.used)
+                .map(|(id, _)| id.clone())
+        {
+            loaded.remove(&least_used);
+        }
+        drop(loaded);
+        Ok((package, docs))
+    }
+
+    /// Builds rustdoc's JSON output and copies it to `cached`. The JSON format is unstable, so
+    /// this needs a nightly toolchain; other toolchains, or a failed JSON build, get HTML docs.
+    async fn build_crate_docs(
         &self,
-        args: &[&str],
-        backtrace: bool,
-    ) -> Result<(Vec<CargoMessage>, Vec<String>)> {
-        let output = Command::new("cargo")
-            .current_dir(self.repository.root())
-            .args(args)
-            .env("RUST_BACKTRACE", if backtrace { "full" } else { "0" })
-            .output()
+        package: &Package,
+        crate_name: &str,
+        cached: &Path,
+        metadata: &Metadata,
+    ) -> Result<CrateDocs> {
+        let spec = format!("{}@{}", package.name, package.version);
+        let doc_dir = metadata.target_directory.join("doc");
+        // Unlocking the JSON output with `RUSTC_BOOTSTRAP` instead would change the flags of the
+        // whole build, making the next check rebuild everything in the shared target dir.
+        if self.nightly_toolchain().await {
+            let output = self
+                .run_cargo_queued(
+                    &[
+                        "rustdoc",
+                        "--offline",
+                        "-p",
+                        &spec,
+                        "--lib",
+                        "--",
+                        "-Z",
+                        "unstable-options",
+                        "--output-format",
+                        "json",
+                    ],
+                    &[],
+                )
+                .await?;
+            let json = doc_dir.join(format!("{}.json", crate_name));
+            if output.status.success() && json.is_file() {
+                if let Some(dir) = cached.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::copy(&json, cached)?;
+                return CrateDocs::load_json(cached, metadata.workspace_root.clone());
+            }
+            tracing::warn!(
+                "rustdoc JSON output failed for {}, falling back to HTML: {}",
+                spec,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let output = self
+            .run_cargo_queued(&["doc", "--offline", "--no-deps", "-p", &spec], &[])
             .await?;
+        if !output.status.success() {
+            bail!(
+                "cargo doc failed for {}: {}",
+                spec,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(CrateDocs::Html(doc_dir.join(crate_name)))
+    }
 
-        let stdout = String::from_utf8(output.stdout)?;
+    /// Whether the project's toolchain is a nightly one, whose rustdoc can produce JSON.
+    async fn nightly_toolchain(&self) -> bool {
+        let root = self.repository.root();
+        let output = output_killable(
+            Command::new("rustc")
+                .current_dir(root)
+                .arg("--version")
+                .envs(self.repository.settings().cargo.env(root)),
+        )
+        .await;
+        output.is_ok_and(|output| {
+            let version = String::from_utf8_lossy(&output.stdout);
+            output.status.success() && (version.contains("-nightly") || version.contains("-dev"))
+        })
+    }
+
+    /// Directories of the packages listed in `ignore_crates`, matching `-` and `_` alike.
+    /// A package at the workspace root is never ignored, since that would hide the whole project.
+    pub async fn ignored_crate_dirs(&self) -> Result<Vec<PathBuf>> {
+        let ignored = self.repository.ignore_crates();
+        if ignored.is_empty() {
+            return Ok(vec![]);
+        }
+        let metadata = self.metadata().await?;
+        let normalize = |name: &str| name.replace('-', "_");
+        let mut dirs = Vec::new();
+        for package in &metadata.packages {
+            if !ignored
+                .iter()
+                .any(|name| normalize(name) == normalize(&package.name))
+            {
+                continue;
+            }
+            if package.root() == metadata.workspace_root {
+                tracing::warn!(
+                    "Not ignoring '{}': it is the workspace root package",
+                    package.name
+                );
+                continue;
+            }
+            dirs.push(package.root().to_path_buf());
+        }
+        Ok(dirs)
+    }
+
+    /// Drops diagnostics whose primary span lies in an ignored crate.
+    async fn without_ignored_crates(
+        &self,
+        diagnostics: Vec<CompilerMessage>,
+    ) -> Vec<CompilerMessage> {
+        let dirs = match self.ignored_crate_dirs().await {
+            Ok(dirs) if !dirs.is_empty() => dirs,
+            Ok(_) => return diagnostics,
+            Err(e) => {
+                tracing::warn!("Could not resolve ignore_crates, not filtering: {}", e);
+                return diagnostics;
+            }
+        };
+        // Span paths are relative to the workspace root, or absolute outside of it.
+        let workspace_root = match self.metadata().await {
+            Ok(metadata) => metadata.workspace_root.clone(),
+            Err(_) => self.repository.root().clone(),
+        };
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| {
+                let span = diagnostic
+                    .spans
+                    .iter()
+                    .find(|span| span.is_primary)
+                    .or(diagnostic.spans.first());
+                span.is_none_or(|span| {
+                    let path = workspace_root.join(&span.file_name);
+                    !dirs.iter().any(|dir| path.starts_with(dir))
+                })
+            })
+            .collect()
+    }
+
+    /// Drops the cached metadata. Called when a `Cargo.toml` or `Cargo.lock` changes.
+    pub fn invalidate_metadata(&self) {
+        let mut cached = self.metadata.cached.lock().unwrap();
+        self.metadata.generation.fetch_add(1, Ordering::SeqCst);
+        *cached = None;
+    }
+
+    /// Queued and running cargo invocations for this project, oldest first.
+    pub fn jobs(&self) -> Vec<JobStatus> {
+        self.jobs.status()
+    }
+
+    /// Runs `cargo <args>` through the project's job queue.
+    /// Only one cargo process runs per project at a time, so runs don't contend for the
+    /// target-dir lock. A request identical to one that is still queued joins it instead of
+    /// queueing a second run.
+    async fn run_cargo_queued(&self, args: &[&str], env: &[(&str, &str)]) -> Result<Arc<Output>> {
+        let mut job_env = self.repository.settings().cargo.env(self.repository.root());
+        job_env.extend(
+            env.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        );
+        let command = CargoJob {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: job_env,
+        };
+        self.jobs
+            .submit(self.repository.root().clone(), command)
+            .await
+    }
+
+    async fn run_cargo_command(&self, args: &[&str], backtrace: bool) -> Result<CargoRun> {
+        let output = self
+            .run_cargo_queued(
+                args,
+                &[("RUST_BACKTRACE", if backtrace { "full" } else { "0" })],
+            )
+            .await?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
 
         let mut messages = Vec::new();
         let mut test_messages = Vec::new();
+        let mut finished = None;
         for line in stdout.lines().filter(|line| !line.is_empty()) {
             match json::from_str::<CargoMessage>(line) {
+                Ok(CargoMessage::BuildFinished { success }) => {
+                    finished = Some(success);
+                }
                 Ok(message) => {
                     messages.push(message);
                 }
@@ -73,38 +672,285 @@ impl CargoRemote {
             }
         }
 
-        Ok((messages, test_messages))
+        Ok(CargoRun {
+            messages,
+            test_messages,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            // Cargo stops before building, without a `build-finished`, when e.g. the manifest
+            // is broken.
+            success: output.status.success() && finished != Some(false),
+        })
+    }
+
+    /// Compiler diagnostics plus what only shows up in cargo's stderr: failed build scripts,
+    /// their `cargo:warning`s and cargo's own errors. A failed run always yields an error.
+    async fn build_report(&self, run: CargoRun) -> BuildReport {
+        let mut diagnostics = compiler_diagnostics(run.messages);
+        diagnostics.extend(self.cargo_stderr_diagnostics(&run.stderr).await);
+        let mut diagnostics = self.without_ignored_crates(diagnostics).await;
+
+        if !run.success && !diagnostics.iter().any(|d| d.level == "error") {
+            let rendered = match run.stderr.trim() {
+                "" => "cargo exited with an error and printed nothing".to_string(),
+                stderr => stderr.to_string(),
+            };
+            diagnostics.push(CompilerMessage::cargo(
+                "error",
+                "cargo failed to build the project".to_string(),
+                rendered,
+                "Cargo.toml".to_string(),
+            ));
+        }
+        BuildReport {
+            diagnostics,
+            success: run.success,
+        }
     }
 
-    /// Runs `cargo check` and returns a human-readable list of rendered diagnostic messages.
-    pub async fn check_rendered(&self) -> Result<Vec<String>> {
-        let diagnostics = self.check_structured().await?;
-        Ok(diagnostics.into_iter().map(|d| d.rendered).collect())
+    async fn cargo_stderr_diagnostics(&self, stderr: &str) -> Vec<CompilerMessage> {
+        let metadata = self.metadata().await.ok();
+        stderr_diagnostics(stderr, metadata.as_deref())
     }
 
     /// Runs `cargo check` with JSON output and returns structured diagnostics.
     /// This is the preferred method for programmatic analysis.
-    pub async fn check_structured(&self) -> Result<Vec<CompilerMessage>> {
-        let (messages, _) = self
+    pub async fn check_structured(&self) -> Result<BuildReport> {
+        let run = self
             .run_cargo_command(&["check", "--message-format=json"], false)
             .await?;
 
-        Ok(messages
-            .into_iter()
-            .filter_map(|message| match message {
-                CargoMessage::CompilerMessage { message } => {
-                    // We only care about diagnostics that have code locations.
-                    if (message.level == "error" || message.level == "warning")
-                        && !message.spans.is_empty()
-                    {
-                        Some(message)
-                    } else {
-                        None
-                    }
-                }
-                _ => None,
-            })
-            .collect::<Vec<_>>())
+        Ok(self.build_report(run).await)
+    }
+
+    /// Runs `cargo clippy` with JSON output and returns structured diagnostics.
+    /// Lint overrides are appended after `--` so they apply to this run only.
+    pub async fn clippy_structured(&self, overrides: &LintOverrides) -> Result<BuildReport> {
+        let lint_args = overrides.to_args();
+        let mut args = vec!["clippy", "--message-format=json"];
+        if !lint_args.is_empty() {
+            args.push("--");
+            args.extend(lint_args.iter().map(String::as_str));
+        }
+        let run = self.run_cargo_command(&args, false).await?;
+
+        Ok(self.build_report(run).await)
+    }
+
+    /// Runs rustfmt in check mode for the target and, if `apply` is set, formats it in place.
+    /// The returned diff always describes the changes relative to the files before this call.
+    pub async fn format(&self, target: &FormatTarget, apply: bool) -> Result<FormatOutcome> {
+        let mut diff = String::new();
+        for check in self.run_rustfmt(target, true).await? {
+            let stdout = String::from_utf8_lossy(&check.stdout);
+            // `--check` exits with 1 when it has a diff to report. Anything else is a real failure.
+            if !check.status.success() && stdout.trim().is_empty() {
+                bail!(
+                    "rustfmt failed: {}",
+                    String::from_utf8_lossy(&check.stderr).trim()
+                );
+            }
+            diff.push_str(&stdout);
+        }
+
+        let is_formatted = diff.trim().is_empty();
+        if apply && !is_formatted {
+            for output in self.run_rustfmt(target, false).await? {
+                if !output.status.success() {
+                    bail!(
+                        "rustfmt failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                }
+            }
+        }
+
+        Ok(FormatOutcome {
+            is_formatted,
+            applied: apply && !is_formatted,
+            diff,
+        })
+    }
+
+    /// Runs `cargo fmt` for the project or a package, or `rustfmt` once per edition for files.
+    async fn run_rustfmt(&self, target: &FormatTarget, check: bool) -> Result<Vec<Arc<Output>>> {
+        let root = self.repository.root();
+        let files = match target {
+            FormatTarget::Files(files) => files,
+            FormatTarget::Project | FormatTarget::Package(_) => {
+                let mut args = vec!["fmt"];
+                match target {
+                    FormatTarget::Package(package) => args.extend(["-p", package.as_str()]),
+                    _ => args.push("--all"),
+                }
+                if check {
+                    args.push("--check");
+                }
+                args.extend(["--", "--color", "never"]);
+                return Ok(vec![self.run_cargo_queued(&args, &[]).await?]);
+            }
+        };
+
+        if files.is_empty() {
+            bail!("No files to format");
+        }
+        // Only files of this project: absolute paths and `..` must not lead out of it.
+        let canonical_root = root.canonicalize()?;
+        let mut by_edition: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for file in files {
+            let path = root
+                .join(file)
+                .canonicalize()
+                .map_err(|e| anyhow!("Cannot format {}: {}", file.display(), e))?;
+            if !path.starts_with(&canonical_root) {
+                bail!("{} is outside the project", file.display());
+            }
+            by_edition
+                .entry(manifest_edition(&path))
+                .or_default()
+                .push(path);
+        }
+
+        let mut outputs = Vec::new();
+        for (edition, paths) in by_edition {
+            let mut command = Command::new("rustfmt");
+            command
+                .envs(self.repository.settings().cargo.env(root))
+                .args(["--edition", &edition])
+                .args(["--color", "never"]);
+            if check {
+                command.arg("--check");
+            }
+            outputs.push(Arc::new(
+                output_killable(command.args(paths).current_dir(root)).await?,
+            ));
+        }
+        Ok(outputs)
+    }
+
+    /// Builds a binary or example through the job queue, then runs it outside of the queue so a
+    /// long-running program doesn't hold up other cargo tools.
+    pub async fn run(&self, target: &RunTarget, options: &RunOptions) -> Result<RunResult> {
+        let root = self.repository.root();
+        let cwd = match &options.cwd {
+            None => root.clone(),
+            Some(cwd) => {
+                // Absolute paths and `..` must not lead out of the project.
+                let path = root.join(cwd).canonicalize().map_err(|e| {
+                    anyhow!("Working directory {} does not exist: {}", cwd.display(), e)
+                })?;
+                if !path.starts_with(root.canonicalize()?) {
+                    bail!("Working directory {} is outside the project", cwd.display());
+                }
+                path
+            }
+        };
+        if !cwd.is_dir() {
+            bail!("Working directory {} is not a directory", cwd.display());
+        }
+
+        let mut args = vec!["build", "--message-format=json"];
+        if let Some(package) = &options.package {
+            args.extend(["-p", package.as_str()]);
+        }
+        let (kind, name) = match target {
+            RunTarget::Bin(Some(name)) => {
+                args.extend(["--bin", name.as_str()]);
+                ("bin", Some(name))
+            }
+            RunTarget::Bin(None) => {
+                args.push("--bins");
+                ("bin", None)
+            }
+            RunTarget::Example(name) => {
+                args.extend(["--example", name.as_str()]);
+                ("example", Some(name))
+            }
+        };
+        if options.release {
+            args.push("--release");
+        }
+        let run = self.run_cargo_command(&args, false).await?;
+        if !run.success {
+            return Ok(RunResult::BuildFailed(self.build_report(run).await));
+        }
+
+        let mut executables = built_executables(&run.messages, kind);
+        if let Some(name) = name {
+            executables.retain(|(built, _)| built == name);
+        }
+        let executable = match executables.as_slice() {
+            [(_, executable)] => executable.clone(),
+            [] => bail!("cargo built no {} to run", kind),
+            several => {
+                let names: Vec<&str> = several.iter().map(|(name, _)| name.as_str()).collect();
+                bail!(
+                    "Several binaries were built ({}); pick one with `bin`",
+                    names.join(", ")
+                );
+            }
+        };
+
+        let mut command = Command::new(&executable);
+        command
+            .current_dir(&cwd)
+            .args(&options.args)
+            .envs(self.repository.settings().cargo.env.clone())
+            .envs(options.env.iter().cloned());
+        let outcome = run_with_timeout(
+            &mut command,
+            executable,
+            options.stdin.clone(),
+            options.timeout,
+        )
+        .await?;
+        Ok(RunResult::Finished(outcome))
+    }
+
+    /// Runs `cargo bench` for the given bench targets (all if empty), passing `filter` on to the
+    /// harness, and collects the timings it prints.
+    pub async fn bench(
+        &self,
+        package: Option<&str>,
+        benches: &[String],
+        filter: Option<&str>,
+    ) -> Result<BenchOutcome> {
+        let mut args = vec!["bench", "--message-format=json"];
+        if let Some(package) = package {
+            args.extend(["-p", package]);
+        }
+        for bench in benches {
+            args.extend(["--bench", bench.as_str()]);
+        }
+        if let Some(filter) = filter {
+            args.extend(["--", filter]);
+        }
+        let run = self.run_cargo_command(&args, false).await?;
+        if !run.success {
+            let stderr = last_lines(run.stderr.lines(), BENCH_FAILURE_LINES);
+            let output = last_lines(
+                run.test_messages.iter().map(String::as_str),
+                BENCH_FAILURE_LINES,
+            );
+            return Ok(BenchOutcome::Failed {
+                report: self.build_report(run).await,
+                stderr,
+                output,
+            });
+        }
+        Ok(BenchOutcome::Finished(parse_bench_output(
+            &run.test_messages,
+        )))
+    }
+
+    /// Saved bench baselines, kept in the target directory next to criterion's own.
+    pub fn bench_baselines(&self) -> BaselineStore {
+        let settings = self.repository.settings();
+        BaselineStore::new(
+            self.repository
+                .root()
+                .join(&settings.cargo.target_dir)
+                .join("bench-baselines"),
+        )
     }
 
     pub async fn test(&self, test_name: Option<String>, backtrace: bool) -> Result<Vec<String>> {
@@ -114,7 +960,720 @@ impl CargoRemote {
             args.push("--nocapture");
             args.push(test_name);
         }
-        let (_, messages) = self.run_cargo_command(&args, backtrace).await?;
-        Ok(messages)
+        let run = self.run_cargo_command(&args, backtrace).await?;
+        Ok(run.test_messages)
+    }
+}
+
+#[derive(Debug)]
+struct LoadedDocs {
+    docs: Arc<CrateDocs>,
+    /// When the build that produced `docs` started.
+    built: SystemTime,
+    /// Last lookup, for dropping the least recently used docs.
+    used: Instant,
+}
+
+#[derive(Debug, Default)]
+struct MetadataCache {
+    /// Held while `cargo metadata` runs so concurrent callers share one run.
+    fetch: tokio::sync::Mutex<()>,
+    cached: Mutex<Option<Arc<Metadata>>>,
+    /// Bumped on every invalidation.
+    generation: AtomicU64,
+}
+
+/// Parsed output of a cargo invocation with `--message-format=json`.
+struct CargoRun {
+    messages: Vec<CargoMessage>,
+    /// Lines of stdout that aren't JSON messages, such as test harness output.
+    test_messages: Vec<String>,
+    stderr: String,
+    success: bool,
+}
+
+/// The command line of a queued cargo invocation. Two jobs with equal `CargoJob`s are coalesced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CargoJob {
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+}
+
+impl CargoJob {
+    fn display(&self) -> String {
+        std::iter::once("cargo")
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Result shared by every caller waiting on a job. Errors are flattened to strings because
+/// `anyhow::Error` can't be cloned.
+type JobResult = Result<Arc<Output>, String>;
+
+#[derive(Debug)]
+struct Job {
+    id: u64,
+    command: CargoJob,
+    queued_at: Instant,
+    started_at: OnceLock<Instant>,
+    /// Tool calls waiting for the result. Guarded by the `JobQueue::jobs` lock.
+    waiters: AtomicUsize,
+    result: watch::Receiver<Option<JobResult>>,
+    abort: OnceLock<AbortHandle>,
+}
+
+/// Per-project scheduler for cargo invocations.
+/// Each job runs in its own task, one at a time in submission order. The task is aborted (and
+/// its process group killed) once every caller waiting on it has gone away.
+#[derive(Debug, Default)]
+struct JobQueue {
+    run_lock: tokio::sync::Mutex<()>,
+    next_id: AtomicU64,
+    jobs: Mutex<Vec<Arc<Job>>>,
+}
+
+impl JobQueue {
+    async fn submit(self: &Arc<Self>, root: PathBuf, command: CargoJob) -> Result<Arc<Output>> {
+        let job = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let pending = jobs
+                .iter()
+                .find(|job| job.started_at.get().is_none() && job.command == command)
+                .cloned();
+            match pending {
+                Some(job) => {
+                    tracing::debug!("Joining queued cargo job {}: {}", job.id, command.display());
+                    job.waiters.fetch_add(1, Ordering::SeqCst);
+                    job
+                }
+                None => {
+                    let job = self.spawn(root, command);
+                    jobs.push(job.clone());
+                    job
+                }
+            }
+        };
+
+        let waiter = JobWaiter {
+            queue: self.clone(),
+            job: job.clone(),
+        };
+        let mut result = job.result.clone();
+        let output = result
+            .wait_for(Option::is_some)
+            .await
+            .map_err(|_| anyhow!("cargo job {} ended without a result", job.id))?
+            .clone()
+            .expect("waited for a result");
+        drop(waiter);
+        output.map_err(|e| anyhow!(e))
+    }
+
+    /// Creates the job and its task. Must be called with the `jobs` lock held.
+    fn spawn(self: &Arc<Self>, root: PathBuf, command: CargoJob) -> Arc<Job> {
+        let (sender, receiver) = watch::channel(None);
+        let job = Arc::new(Job {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            command,
+            queued_at: Instant::now(),
+            started_at: OnceLock::new(),
+            waiters: AtomicUsize::new(1),
+            result: receiver,
+            abort: OnceLock::new(),
+        });
+
+        let queue = self.clone();
+        let task_job = job.clone();
+        let handle = tokio::spawn(async move {
+            let _running = queue.run_lock.lock().await;
+            {
+                // Mark the job as started under the jobs lock so nobody joins it from now on:
+                // a caller arriving later may have edited files since this run was requested.
+                let _jobs = queue.jobs.lock().unwrap();
+                let _ = task_job.started_at.set(Instant::now());
+            }
+            tracing::debug!(
+                "Running cargo job {}: {}",
+                task_job.id,
+                task_job.command.display()
+            );
+
+            let output = output_killable(
+                Command::new("cargo")
+                    .current_dir(&root)
+                    .args(&task_job.command.args)
+                    .envs(task_job.command.env.iter().cloned()),
+            )
+            .await
+            .map(Arc::new)
+            .map_err(|e| format!("Failed to run `{}`: {}", task_job.command.display(), e));
+
+            queue.remove(task_job.id);
+            sender.send_replace(Some(output));
+        });
+        let _ = job.abort.set(handle.abort_handle());
+        job
+    }
+
+    fn remove(&self, id: u64) {
+        self.jobs.lock().unwrap().retain(|job| job.id != id);
+    }
+
+    fn status(&self) -> Vec<JobStatus> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|job| {
+                let (state, since) = match job.started_at.get() {
+                    Some(started_at) => (JobState::Running, *started_at),
+                    None => (JobState::Queued, job.queued_at),
+                };
+                JobStatus {
+                    id: job.id,
+                    command: job.command.display(),
+                    state,
+                    waiters: job.waiters.load(Ordering::SeqCst),
+                    elapsed_secs: since.elapsed().as_secs(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Registered interest in a job. Dropping the last waiter of an unfinished job aborts it.
+struct JobWaiter {
+    queue: Arc<JobQueue>,
+    job: Arc<Job>,
+}
+
+impl Drop for JobWaiter {
+    fn drop(&mut self) {
+        let mut jobs = self.queue.jobs.lock().unwrap();
+        if self.job.waiters.fetch_sub(1, Ordering::SeqCst) == 1 {
+            jobs.retain(|job| job.id != self.job.id);
+            // A no-op when the job has already finished.
+            if let Some(abort) = self.job.abort.get() {
+                if self.job.result.borrow().is_none() {
+                    tracing::debug!("Cancelling cargo job {}: no callers left", self.job.id);
+                }
+                abort.abort();
+            }
+        }
+    }
+}
+
+/// Spawns `command` in its own process group and collects its output.
+/// If the returned future is dropped early (the tool call was cancelled or timed out), the whole
+/// group is killed so build scripts and rustc children don't outlive the call.
+async fn output_killable(command: &mut Command) -> Result<Output> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let child = command.spawn()?;
+    let mut group = ProcessGroupGuard(child.id());
+    let output = child.wait_with_output().await?;
+    group.0 = None;
+    Ok(output)
+}
+
+/// Runs a program with `stdin` as its input, killing its process group after `timeout` or when
+/// the returned future is dropped. The output captured until then is kept either way.
+async fn run_with_timeout(
+    command: &mut Command,
+    executable: PathBuf,
+    stdin: Option<String>,
+    timeout: Duration,
+) -> Result<RunOutcome> {
+    command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let started = Instant::now();
+    let mut child = command
+        .spawn()
+        .map_err(|e| anyhow!("Failed to start {}: {}", executable.display(), e))?;
+    let mut group = ProcessGroupGuard(child.id());
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        // The program may exit without reading everything, so a broken pipe is fine.
+        tokio::spawn(async move {
+            let _ = pipe.write_all(input.as_bytes()).await;
+        });
+    }
+    let stdout = child.stdout.take().map(capture);
+    let stderr = child.stderr.take().map(capture);
+
+    let (status, timed_out) = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => {
+            group.0 = None;
+            (status?, false)
+        }
+        Err(_) => {
+            drop(group);
+            child.start_kill()?;
+            (child.wait().await?, true)
+        }
+    };
+    let duration_ms = started.elapsed().as_millis();
+
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let signal = None;
+    Ok(RunOutcome {
+        executable,
+        exit_code: status.code(),
+        signal,
+        timed_out,
+        duration_ms,
+        stdout: collect_captured(stdout).await,
+        stderr: collect_captured(stderr).await,
+    })
+}
+
+/// Reads a pipe into a `HeadTail` in the background.
+fn capture(mut pipe: impl AsyncRead + Unpin + Send + 'static) -> tokio::task::JoinHandle<HeadTail> {
+    tokio::spawn(async move {
+        let mut output = HeadTail::default();
+        let mut buffer = [0; 8192];
+        while let Ok(read) = pipe.read(&mut buffer).await {
+            if read == 0 {
+                break;
+            }
+            output.push(&buffer[..read]);
+        }
+        output
+    })
+}
+
+async fn collect_captured(capture: Option<tokio::task::JoinHandle<HeadTail>>) -> String {
+    let Some(capture) = capture else {
+        return String::new();
+    };
+    // A process that detached from the group can keep the pipe open. Don't wait for it forever.
+    match tokio::time::timeout(Duration::from_secs(1), capture).await {
+        Ok(Ok(output)) => output.render(),
+        _ => "[output unavailable: the pipe was held open after the program exited]".to_string(),
+    }
+}
+
+/// The last `count` of `lines`, joined back together.
+fn last_lines<'a>(lines: impl IntoIterator<Item = &'a str>, count: usize) -> String {
+    let lines: Vec<&str> = lines.into_iter().collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+/// The first and last `RUN_OUTPUT_LIMIT / 2` bytes of a stream.
+#[derive(Default)]
+struct HeadTail {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    total: usize,
+}
+
+impl HeadTail {
+    fn push(&mut self, mut bytes: &[u8]) {
+        let half = RUN_OUTPUT_LIMIT / 2;
+        self.total += bytes.len();
+        let into_head = bytes.len().min(half - self.head.len());
+        self.head.extend_from_slice(&bytes[..into_head]);
+        bytes = &bytes[into_head..];
+        self.tail.extend(bytes);
+        let excess = self.tail.len().saturating_sub(half);
+        self.tail.drain(..excess);
+    }
+
+    fn render(&self) -> String {
+        let tail: Vec<u8> = self.tail.iter().copied().collect();
+        let omitted = self.total - self.head.len() - tail.len();
+        if omitted == 0 {
+            return String::from_utf8_lossy(&[self.head.as_slice(), &tail].concat()).into_owned();
+        }
+        format!(
+            "{}\n... [{} bytes omitted] ...\n{}",
+            String::from_utf8_lossy(&self.head),
+            omitted,
+            String::from_utf8_lossy(&tail)
+        )
+    }
+}
+
+/// Name and path of each executable of `kind` (`bin` or `example`) among the built artifacts.
+fn built_executables(messages: &[CargoMessage], kind: &str) -> Vec<(String, PathBuf)> {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            CargoMessage::CompilerArtifact(artifact) => Some(artifact),
+            _ => None,
+        })
+        .filter(|artifact| {
+            artifact["target"]["kind"]
+                .as_array()
+                .is_some_and(|kinds| kinds.iter().any(|k| k == kind))
+        })
+        .filter_map(|artifact| {
+            let name = artifact["target"]["name"].as_str()?;
+            let executable = artifact["executable"].as_str()?;
+            Some((name.to_string(), PathBuf::from(executable)))
+        })
+        .collect()
+}
+
+/// The latest modification time of the Rust sources and manifest of a package, not looking into
+/// hidden directories or `target`.
+fn newest_source_modification(dir: &Path) -> Option<SystemTime> {
+    let mut newest = None;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if path.is_dir() {
+                if !name.starts_with('.') && name != "target" {
+                    pending.push(path);
+                }
+            } else if (name.ends_with(".rs") || name == "Cargo.toml")
+                && let Ok(modified) = entry.metadata().and_then(|meta| meta.modified())
+            {
+                newest = newest.max(Some(modified));
+            }
+        }
+    }
+    newest
+}
+
+/// Kills the process group led by the contained pid when dropped.
+struct ProcessGroupGuard(Option<u32>);
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            tracing::debug!("Killing cargo process group {}", pid);
+            // SAFETY: `killpg` has no memory-safety preconditions. The group was created for the
+            // child by `process_group(0)`, so its id is the child's pid.
+            unsafe {
+                libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Keeps the errors and warnings that point at a code location. Errors without one, like a
+/// native library that can't be found, are placed at the start of their target's root file.
+fn compiler_diagnostics(messages: Vec<CargoMessage>) -> Vec<CompilerMessage> {
+    messages
+        .into_iter()
+        .filter_map(|message| match message {
+            CargoMessage::CompilerMessage { message, target } => {
+                if message.level != "error" && message.level != "warning" {
+                    return None;
+                }
+                if !message.spans.is_empty() {
+                    return Some(message);
+                }
+                // The "aborting due to N previous errors" summaries repeat what we have.
+                if message.level != "error" || message.message.starts_with("aborting due to") {
+                    return None;
+                }
+                let file_name = target?.src_path.to_string_lossy().into_owned();
+                let mut located = CompilerMessage::cargo(
+                    "error",
+                    message.message.clone(),
+                    message.rendered.clone(),
+                    file_name,
+                );
+                located.code = message.code;
+                located.children = message.children;
+                Some(located)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Diagnostics for the failures cargo reports on stderr: build scripts, manifests and dependency
+/// resolution. Build script messages are located at the package's build script when `metadata`
+/// knows it.
+fn stderr_diagnostics(stderr: &str, metadata: Option<&Metadata>) -> Vec<CompilerMessage> {
+    static BUILD_SCRIPT_REGEX: OnceLock<Regex> = OnceLock::new();
+    static WARNING_REGEX: OnceLock<Regex> = OnceLock::new();
+    let build_script_regex = BUILD_SCRIPT_REGEX.get_or_init(|| {
+        Regex::new(r"^failed to run custom build command for `(\S+) v(\S+)(?: \((.*)\))?`").unwrap()
+    });
+    let warning_regex =
+        WARNING_REGEX.get_or_init(|| Regex::new(r"^warning: ([\w-]+)@(\S+): (.*)$").unwrap());
+
+    let build_script = |name: &str, version: &str, dir: Option<&str>| {
+        let path = metadata
+            .and_then(|metadata| {
+                let package = metadata
+                    .packages
+                    .iter()
+                    .find(|package| package.name == name && package.version == version)?;
+                let target = package
+                    .targets
+                    .iter()
+                    .find(|target| target.kind.iter().any(|kind| kind == "custom-build"))?;
+                let root = &metadata.workspace_root;
+                Some(
+                    target
+                        .src_path
+                        .strip_prefix(root)
+                        .unwrap_or(&target.src_path)
+                        .to_path_buf(),
+                )
+            })
+            .or_else(|| dir.map(|dir| Path::new(dir).join("build.rs")))
+            .unwrap_or_else(|| PathBuf::from("Cargo.toml"));
+        path.to_string_lossy().into_owned()
+    };
+
+    let mut diagnostics = Vec::new();
+    for block in stderr_blocks(stderr) {
+        if let Some(error) = block.strip_prefix("error: ") {
+            let first_line = error.lines().next().unwrap_or_default();
+            // rustc's errors already arrived as compiler messages.
+            if first_line.starts_with("could not compile") {
+                continue;
+            }
+            let file_name = match build_script_regex.captures(first_line) {
+                Some(captures) => build_script(
+                    &captures[1],
+                    &captures[2],
+                    captures.get(3).map(|m| m.as_str()),
+                ),
+                None => "Cargo.toml".to_string(),
+            };
+            diagnostics.push(CompilerMessage::cargo(
+                "error",
+                first_line.to_string(),
+                block.clone(),
+                file_name,
+            ));
+        } else if let Some(captures) = block
+            .lines()
+            .next()
+            .and_then(|first_line| warning_regex.captures(first_line))
+        {
+            diagnostics.push(CompilerMessage::cargo(
+                "warning",
+                format!("build script: {}", &captures[3]),
+                block.clone(),
+                build_script(&captures[1], &captures[2], None),
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// Splits cargo's stderr into its top-level messages. A message runs until the next unindented
+/// `error`/`warning` line or progress line; its `Caused by:` chain and notes stay with it.
+fn stderr_blocks(stderr: &str) -> Vec<String> {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut in_block = false;
+    for line in stderr.lines() {
+        if line.starts_with("error") || line.starts_with("warning") {
+            blocks.push(line.to_string());
+            in_block = true;
+        } else if is_progress_line(line) {
+            in_block = false;
+        } else if in_block && let Some(block) = blocks.last_mut() {
+            block.push('\n');
+            block.push_str(line);
+        }
+    }
+    blocks
+        .into_iter()
+        .map(|block| block.trim_end().to_string())
+        .collect()
+}
+
+/// Cargo's status lines, like `   Compiling foo v0.1.0`, right-align their verb to column 12.
+fn is_progress_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let Some((verb, _)) = trimmed.split_once(' ') else {
+        return false;
+    };
+    line.len() - trimmed.len() + verb.len() == 12
+        && verb.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+        && verb.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Finds the edition of the crate that owns `file` by walking up to the nearest manifest.
+/// Falls back to the workspace `[workspace.package]` edition when the crate inherits it.
+fn manifest_edition(file: &Path) -> String {
+    let mut inherits = false;
+    for dir in file.ancestors().skip(1) {
+        let Ok(content) = std::fs::read_to_string(dir.join("Cargo.toml")) else {
+            continue;
+        };
+        let Ok(manifest) = content.parse::<toml::Table>() else {
+            continue;
+        };
+        if !inherits {
+            match manifest.get("package").and_then(|p| p.get("edition")) {
+                Some(toml::Value::String(edition)) => return edition.clone(),
+                Some(toml::Value::Table(_)) => inherits = true,
+                _ => {}
+            }
+        }
+        if let Some(edition) = manifest
+            .get("workspace")
+            .and_then(|w| w.get("package"))
+            .and_then(|p| p.get("edition"))
+            .and_then(|e| e.as_str())
+        {
+            return edition.to_string();
+        }
+        if manifest.contains_key("package") && !inherits {
+            break;
+        }
+    }
+    "2015".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(diagnostics: &[CompilerMessage]) -> Vec<(&str, &str, &str)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.level.as_str(),
+                    diagnostic.message.as_str(),
+                    diagnostic.spans[0].file_name.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_failing_build_script() {
+        let stderr = "   Compiling libc v0.2.155
+   Compiling protos v0.1.0 (/home/dev/app/protos)
+error: failed to run custom build command for `protos v0.1.0 (/home/dev/app/protos)`
+
+Caused by:
+  process didn't exit successfully: `/home/dev/app/target/debug/build/protos-3f2a9c1d0b7e4a55/build-script-build` (exit status: 101)
+  --- stdout
+  cargo:rerun-if-changed=proto/api.proto
+
+  --- stderr
+  thread 'main' panicked at protos/build.rs:4:10:
+  protoc not found
+  note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+warning: build failed, waiting for other jobs to finish...
+";
+        let diagnostics = stderr_diagnostics(stderr, None);
+        assert_eq!(
+            summary(&diagnostics),
+            [(
+                "error",
+                "failed to run custom build command for `protos v0.1.0 (/home/dev/app/protos)`",
+                "/home/dev/app/protos/build.rs",
+            )]
+        );
+        assert!(diagnostics[0].rendered.contains("protoc not found"));
+        assert!(!diagnostics[0].rendered.contains("waiting for other jobs"));
+    }
+
+    #[test]
+    fn reports_unresolvable_dependency() {
+        let stderr = "    Updating crates.io index
+error: no matching package named `serde_jsonn` found
+location searched: registry `crates-io`
+required by package `app v0.1.0 (/home/dev/app)`
+";
+        let diagnostics = stderr_diagnostics(stderr, None);
+        assert_eq!(
+            summary(&diagnostics),
+            [(
+                "error",
+                "no matching package named `serde_jsonn` found",
+                "Cargo.toml",
+            )]
+        );
+        assert!(diagnostics[0].rendered.contains("required by package"));
+    }
+
+    #[test]
+    fn reports_build_script_warnings() {
+        let stderr = "   Compiling app v0.1.0 (/home/dev/app)
+warning: app@0.1.0: bundled sqlite is outdated
+warning: app@0.1.0: set SQLITE3_LIB_DIR to use the system library
+  (found /usr/lib/libsqlite3.so)
+warning: `app` (lib) generated 1 warning
+    Finished `dev` profile [unoptimized + debuginfo] target(s) in 1.02s
+";
+        let diagnostics = stderr_diagnostics(stderr, None);
+        assert_eq!(
+            summary(&diagnostics),
+            [
+                (
+                    "warning",
+                    "build script: bundled sqlite is outdated",
+                    "Cargo.toml",
+                ),
+                (
+                    "warning",
+                    "build script: set SQLITE3_LIB_DIR to use the system library",
+                    "Cargo.toml",
+                ),
+            ]
+        );
+        assert!(
+            diagnostics[1]
+                .rendered
+                .ends_with("(found /usr/lib/libsqlite3.so)")
+        );
+    }
+
+    #[test]
+    fn skips_could_not_compile() {
+        let stderr = "    Checking app v0.1.0 (/home/dev/app)
+error: could not compile `app` (lib) due to 2 previous errors
+";
+        assert!(stderr_diagnostics(stderr, None).is_empty());
+    }
+
+    #[test]
+    fn keeps_last_lines() {
+        assert_eq!(last_lines("a\nb\nc".lines(), 2), "b\nc");
+        assert_eq!(last_lines("a\nb".lines(), 5), "a\nb");
+        assert_eq!(last_lines("".lines(), 5), "");
+    }
+
+    #[test]
+    fn recognizes_progress_lines() {
+        assert!(is_progress_line("   Compiling app v0.1.0 (/home/dev/app)"));
+        assert!(is_progress_line("    Updating crates.io index"));
+        assert!(is_progress_line(
+            "    Finished `dev` profile [unoptimized + debuginfo] target(s) in 1.02s"
+        ));
+        assert!(!is_progress_line("  process didn't exit successfully"));
+        assert!(!is_progress_line("Caused by:"));
+        assert!(!is_progress_line(""));
     }
 }
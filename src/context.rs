@@ -3,15 +3,14 @@ use dashmap::DashMap;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
 
-use crate::cargo_remote::CargoRemote;
-use crate::lsp::LspNotification;
+use crate::cargo_remote::{CargoRemote, CompilerMessage};
+use crate::lsp::{LspNotification, Readiness};
 use crate::mcp::McpNotification;
 use crate::{
     lsp::RustAnalyzerLsp,
-    project::{Project, TransportType},
+    project::{Project, ProjectSettings, TransportType},
 };
 use flume::Sender;
 use serde::{Deserialize, Serialize};
@@ -36,6 +35,8 @@ impl ContextNotification {
     pub fn notification_path(&self) -> PathBuf {
         match self {
             ContextNotification::Lsp(LspNotification::Indexing { project, .. }) => project.clone(),
+            ContextNotification::Lsp(LspNotification::ManifestChanged { project }) => project.clone(),
+            ContextNotification::Lsp(LspNotification::Status { project, .. }) => project.clone(),
             ContextNotification::Mcp(McpNotification::Response { project, .. }) => project.clone(),
             ContextNotification::Mcp(McpNotification::CodeActionsUpdated { project, .. }) => project.clone(),
             ContextNotification::ProjectAdded(project) => project.clone(),
@@ -90,6 +91,20 @@ impl ContextNotification {
                     "✅ LSP Indexing: Finished".to_string()
                 }
             }
+            ContextNotification::Lsp(LspNotification::ManifestChanged { .. }) => {
+                "📦 Cargo manifest changed, workspace metadata will be reloaded".to_string()
+            }
+            ContextNotification::Lsp(LspNotification::Status { quiescent, message, .. }) => {
+                let status = if *quiescent {
+                    "✅ rust-analyzer ready"
+                } else {
+                    "⏳ rust-analyzer loading workspace"
+                };
+                match message {
+                    Some(message) => format!("{}: {}", status, message),
+                    None => status.to_string(),
+                }
+            }
             ContextNotification::Mcp(McpNotification::Response { content, .. }) => {
                 format!("MCP Response: {:?}", content)
             }
@@ -127,7 +142,9 @@ pub struct ProjectContext {
     pub project: Project,
     pub lsp: RustAnalyzerLsp,
     pub cargo_remote: CargoRemote,
-    pub is_indexing_lsp: AtomicBool,
+    pub readiness: Readiness,
+    /// Diagnostics of the latest `check_project`, the baseline for its `since_last` mode.
+    pub last_check: Mutex<Option<Vec<CompilerMessage>>>,
 }
 
 #[derive(Clone)]
@@ -146,7 +163,7 @@ impl Context {
         config_path: PathBuf,
         notifier: Sender<ContextNotification>,
     ) -> Self {
-        let (lsp_sender, lsp_receiver) = flume::unbounded();
+        let (lsp_sender, lsp_receiver) = flume::unbounded::<LspNotification>();
         let (mcp_sender, mcp_receiver) = flume::unbounded();
 
         let projects = Arc::new(DashMap::<PathBuf, Arc<ProjectContext>>::new());
@@ -161,12 +178,26 @@ impl Context {
                             tracing::error!("Failed to send MCP notification: {}", e);
                         }
                     }
-                    Ok(ref notification @ LspNotification::Indexing { ref project, is_indexing, .. }) = lsp_receiver.recv_async() => {
+                    Ok(notification) = lsp_receiver.recv_async() => {
                         if let Err(e) = cloned_notifier.send(ContextNotification::Lsp(notification.clone())) {
                             tracing::error!("Failed to send LSP notification: {}", e);
                         }
-                        if let Some(project) = cloned_projects.get(project) {
-                            project.value().is_indexing_lsp.store(is_indexing, std::sync::atomic::Ordering::Relaxed);
+                        match notification {
+                            LspNotification::Indexing { ref project, is_indexing, ref progress } => {
+                                if let Some(project) = cloned_projects.get(project) {
+                                    project.value().readiness.update_indexing(is_indexing, progress.clone());
+                                }
+                            }
+                            LspNotification::Status { ref project, quiescent, ref message } => {
+                                if let Some(project) = cloned_projects.get(project) {
+                                    project.value().readiness.update_status(quiescent, message.clone());
+                                }
+                            }
+                            LspNotification::ManifestChanged { ref project } => {
+                                if let Some(project) = cloned_projects.get(project) {
+                                    project.value().cargo_remote.invalidate_metadata();
+                                }
+                            }
                         }
                     }
                     else => {
@@ -237,6 +268,7 @@ impl Context {
                 let ser_project = SerProject {
                     root: pc.project.root().clone(),
                     ignore_crates: pc.project.ignore_crates().to_vec(),
+                    settings: pc.project.settings().clone(),
                 };
                 (path, ser_project)
             })
@@ -300,6 +332,7 @@ impl Context {
             let project = Project {
                 root: ser_project.root.clone(),
                 ignore_crates: ser_project.ignore_crates,
+                settings: ser_project.settings,
             };
             // Validate project root before adding
             if !project.root().exists() || !project.root().is_dir() {
@@ -309,9 +342,15 @@ impl Context {
                 );
                 continue;
             }
-            // We need to canonicalize again as the stored path might be relative or different
+            // We need to canonicalize again as the stored path might be relative or different,
+            // but keep the stored per-project configuration.
             match Project::new(project.root()) {
                 Ok(new_project) => {
+                    let new_project = Project {
+                        ignore_crates: project.ignore_crates.clone(),
+                        settings: project.settings.clone(),
+                        ..new_project
+                    };
                     if let Err(e) = self.add_project(new_project).await {
                         tracing::error!(
                             "Failed to add project {:?} from config: {}",
@@ -336,13 +375,18 @@ impl Context {
     /// Add a new project to the context
     pub async fn add_project(&self, project: Project) -> Result<()> {
         let root = project.root().clone();
-        let lsp = RustAnalyzerLsp::new(&project, self.lsp_sender.clone()).await?;
         let cargo_remote = CargoRemote::new(project.clone());
+        let excluded_dirs = cargo_remote.ignored_crate_dirs().await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to resolve ignore_crates for {:?}: {}", root, e);
+            vec![]
+        });
+        let lsp = RustAnalyzerLsp::new(&project, excluded_dirs, self.lsp_sender.clone()).await?;
         let project_context = Arc::new(ProjectContext {
             project,
             lsp,
             cargo_remote,
-            is_indexing_lsp: AtomicBool::new(true),
+            readiness: Readiness::default(),
+            last_check: Mutex::new(None),
         });
 
         self.projects.insert(root.clone(), project_context);
@@ -505,6 +549,8 @@ pub struct SerConfig {
 pub struct SerProject {
     pub root: PathBuf,
     pub ignore_crates: Vec<String>,
+    #[serde(flatten)]
+    pub settings: ProjectSettings,
 }
 
 async fn project_descriptions(
@@ -523,9 +569,7 @@ async fn project_descriptions(
                     .unwrap()
                     .to_string_lossy()
                     .to_string(),
-                is_indexing_lsp: project
-                    .is_indexing_lsp
-                    .load(std::sync::atomic::Ordering::Relaxed),
+                is_indexing_lsp: !project.readiness.is_ready(),
             }
         })
         .collect()
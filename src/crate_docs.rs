@@ -0,0 +1,508 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Longest signature taken from the source before it is cut off.
+const MAX_SIGNATURE_LEN: usize = 600;
+/// Longest text taken from an HTML page.
+const MAX_HTML_TEXT_LEN: usize = 8000;
+
+/// The documentation of one crate: rustdoc's JSON output, or its HTML pages where the toolchain
+/// couldn't produce JSON.
+#[derive(Debug)]
+pub enum CrateDocs {
+    Json {
+        doc: Value,
+        /// Span file names are relative to this directory unless absolute.
+        workspace_root: PathBuf,
+    },
+    /// The crate's directory under `target/doc`.
+    Html(PathBuf),
+}
+
+/// What `CrateDocs::lookup` found for a path.
+#[derive(Debug, Serialize)]
+pub struct ItemDoc {
+    pub path: String,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<Value>,
+    /// `file:line` of the definition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Module items, fields, variants, trait items or inherent methods.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<MemberDoc>,
+    /// Traits implemented by a type, leaving out auto traits and blanket impls.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trait_impls: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberDoc {
+    pub name: String,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// The first paragraph of the member's docs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+impl CrateDocs {
+    pub fn load_json(file: &Path, workspace_root: PathBuf) -> Result<Self> {
+        let content =
+            std::fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let doc = serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse {}", file.display()))?;
+        Ok(Self::Json {
+            doc,
+            workspace_root,
+        })
+    }
+
+    /// Looks up an item by its path below the crate root, e.g. `["Sender", "send_async"]`.
+    /// Re-exports are followed, so both an item's canonical path and the one it is exported
+    /// under work. An empty path is the crate root.
+    pub fn lookup(&self, crate_name: &str, path: &[&str]) -> Result<ItemDoc> {
+        let display = std::iter::once(crate_name)
+            .chain(path.iter().copied())
+            .collect::<Vec<_>>()
+            .join("::");
+        match self {
+            Self::Json {
+                doc,
+                workspace_root,
+            } => {
+                let docs = JsonDocs {
+                    doc,
+                    workspace_root,
+                };
+                match docs.resolve(crate_name, path) {
+                    Some(item) => Ok(docs.item_doc(display, item)),
+                    None => bail!("{}", docs.not_found(&display, path)),
+                }
+            }
+            Self::Html(dir) => html_lookup(dir, display, path),
+        }
+    }
+}
+
+struct JsonDocs<'a> {
+    doc: &'a Value,
+    workspace_root: &'a Path,
+}
+
+impl<'a> JsonDocs<'a> {
+    fn item(&self, id: &Value) -> Option<&'a Value> {
+        self.doc["index"].get(id_key(id)?)
+    }
+
+    fn resolve(&self, crate_name: &str, path: &[&str]) -> Option<&'a Value> {
+        // Canonical paths are listed directly; others are found by walking the module tree.
+        let canonical = self.doc["paths"].as_object()?.iter().find(|(_, summary)| {
+            summary["crate_id"] == 0
+                && summary["path"].as_array().is_some_and(|segments| {
+                    segments.len() == path.len() + 1
+                        && segments[0] == crate_name
+                        && segments[1..]
+                            .iter()
+                            .zip(path)
+                            .all(|(segment, name)| segment.as_str() == Some(*name))
+                })
+        });
+        if let Some(item) = canonical.and_then(|(id, _)| self.doc["index"].get(id)) {
+            return Some(item);
+        }
+
+        let mut item = self.item(&self.doc["root"])?;
+        for segment in path {
+            item = self.child(item, segment)?;
+        }
+        Some(item)
+    }
+
+    /// The member of a module or type named `name`, looking through re-exports and into the
+    /// modules of glob re-exports.
+    fn child(&self, item: &'a Value, name: &str) -> Option<&'a Value> {
+        let children = self.children(item);
+        let direct = children.iter().find_map(|child| {
+            if child["name"] == name {
+                return Some(*child);
+            }
+            let import = &child["inner"]["use"];
+            if import["name"] == name && import["is_glob"] != true {
+                return self.item(&import["id"]);
+            }
+            None
+        });
+        direct.or_else(|| {
+            children.iter().find_map(|child| {
+                let import = &child["inner"]["use"];
+                let module = self
+                    .item(&import["id"])
+                    .filter(|_| import["is_glob"] == true)?;
+                // A module re-exporting itself would loop forever.
+                (module != item).then(|| self.child(module, name)).flatten()
+            })
+        })
+    }
+
+    fn children(&self, item: &'a Value) -> Vec<&'a Value> {
+        let (kind, inner) = item_kind(item);
+        let ids = |key: &str| -> Vec<&'a Value> {
+            inner[key]
+                .as_array()
+                .map(|ids| ids.iter().filter_map(|id| self.item(id)).collect())
+                .unwrap_or_default()
+        };
+        match kind {
+            "module" | "trait" => ids("items"),
+            "enum" => {
+                let mut children = ids("variants");
+                children.extend(self.inherent_methods(item));
+                children
+            }
+            "struct" | "union" => {
+                let mut children = self.fields(item);
+                children.extend(self.inherent_methods(item));
+                children
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn fields(&self, item: &'a Value) -> Vec<&'a Value> {
+        let (_, inner) = item_kind(item);
+        let fields = match &inner["kind"] {
+            Value::Object(kind) => kind
+                .get("plain")
+                .map(|plain| &plain["fields"])
+                .or_else(|| kind.get("tuple")),
+            _ => inner.get("fields"),
+        };
+        fields
+            .and_then(Value::as_array)
+            .map(|ids| ids.iter().filter_map(|id| self.item(id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Impl blocks of a type, not counting auto traits and blanket impls.
+    fn impls(&self, item: &'a Value) -> impl Iterator<Item = &'a Value> {
+        let (_, inner) = item_kind(item);
+        inner["impls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.item(id))
+            .map(|item| &item["inner"]["impl"])
+            .filter(|block| block["is_synthetic"] != true && block["blanket_impl"].is_null())
+    }
+
+    fn inherent_methods(&self, item: &'a Value) -> Vec<&'a Value> {
+        self.impls(item)
+            .filter(|block| block["trait"].is_null())
+            .flat_map(|block| block["items"].as_array().into_iter().flatten())
+            .filter_map(|id| self.item(id))
+            .collect()
+    }
+
+    fn trait_impls(&self, item: &'a Value) -> Vec<String> {
+        let mut traits: Vec<String> = self
+            .impls(item)
+            .filter_map(|block| {
+                let path = &block["trait"];
+                let name = path["path"].as_str().or(path["name"].as_str())?;
+                let negative = if block["is_negative"] == true {
+                    "!"
+                } else {
+                    ""
+                };
+                Some(format!("{}{}", negative, name))
+            })
+            .collect();
+        traits.sort();
+        traits.dedup();
+        traits
+    }
+
+    fn item_doc(&self, path: String, item: &'a Value) -> ItemDoc {
+        let (kind, _) = item_kind(item);
+        let members = self
+            .children(item)
+            .into_iter()
+            .filter_map(|child| self.member_doc(child))
+            .collect();
+        let trait_impls = match kind {
+            "struct" | "enum" | "union" => self.trait_impls(item),
+            _ => Vec::new(),
+        };
+        ItemDoc {
+            path,
+            kind: kind.to_string(),
+            signature: (kind != "module").then(|| self.signature(item)).flatten(),
+            docs: item["docs"].as_str().map(str::to_string),
+            deprecation: Some(item["deprecation"].clone()).filter(|d| !d.is_null()),
+            source: self.source(item),
+            members,
+            trait_impls,
+        }
+    }
+
+    fn member_doc(&self, item: &'a Value) -> Option<MemberDoc> {
+        let (kind, inner) = item_kind(item);
+        if kind == "use" {
+            let name = inner["name"].as_str()?;
+            let target = self.item(&inner["id"]);
+            return Some(MemberDoc {
+                name: name.to_string(),
+                kind: target
+                    .map_or("use", |target| item_kind(target).0)
+                    .to_string(),
+                signature: None,
+                summary: target.and_then(|target| summary(target["docs"].as_str()?)),
+            });
+        }
+        Some(MemberDoc {
+            name: item["name"].as_str()?.to_string(),
+            kind: kind.to_string(),
+            signature: self.signature(item),
+            summary: item["docs"].as_str().and_then(summary),
+        })
+    }
+
+    fn source(&self, item: &Value) -> Option<String> {
+        let span = &item["span"];
+        Some(format!(
+            "{}:{}",
+            self.span_file(span)?.display(),
+            span["begin"][0].as_u64()?
+        ))
+    }
+
+    fn span_file(&self, span: &Value) -> Option<PathBuf> {
+        Some(self.workspace_root.join(span["filename"].as_str()?))
+    }
+
+    /// The declaration of an item as written in its source, without body, e.g.
+    /// `pub async fn send_async(&self, item: T) -> Result<(), SendError<T>>`.
+    /// `None` for items produced by macros, whose spans don't point at a declaration.
+    fn signature(&self, item: &Value) -> Option<String> {
+        let name = item["name"].as_str()?;
+        let span = &item["span"];
+        let begin = span["begin"][0].as_u64()? as usize;
+        let begin_column = span["begin"][1].as_u64()? as usize;
+        let end = span["end"][0].as_u64()? as usize;
+        let source = std::fs::read_to_string(self.span_file(span)?).ok()?;
+        let lines: Vec<&str> = source
+            .lines()
+            .skip(begin.checked_sub(1)?)
+            .take(end + 1 - begin)
+            .collect();
+        let first = lines.first()?;
+        let first = first.get(begin_column.saturating_sub(1)..).unwrap_or(first);
+        let text = std::iter::once(first)
+            .chain(lines[1..].iter().copied())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let kind = item_kind(item).0;
+        let declaration = match kind {
+            // Fields and variants are short and their whole span is the declaration.
+            "struct_field" | "variant" => text.as_str(),
+            _ => match text.find(['{', ';']) {
+                Some(end) => &text[..end],
+                None => text.as_str(),
+            },
+        };
+        let declaration = declaration
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end_matches(',')
+            .to_string();
+        if !declaration.contains(name) {
+            return None;
+        }
+        Some(match declaration.char_indices().nth(MAX_SIGNATURE_LEN) {
+            Some((cut, _)) => format!("{} ...", &declaration[..cut]),
+            None => declaration,
+        })
+    }
+
+    fn not_found(&self, display: &str, path: &[&str]) -> String {
+        let Some(last) = path.last() else {
+            return format!("'{}' is not documented", display);
+        };
+        let mut similar: Vec<String> = self.doc["paths"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, summary)| summary["crate_id"] == 0)
+            .filter_map(|(_, summary)| {
+                let segments: Vec<&str> = summary["path"]
+                    .as_array()?
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect();
+                segments
+                    .last()?
+                    .eq_ignore_ascii_case(last)
+                    .then(|| segments.join("::"))
+            })
+            .collect();
+        similar.sort();
+        if similar.is_empty() {
+            format!("No public item '{}' found", display)
+        } else {
+            format!(
+                "No public item '{}' found. Items with that name: {}",
+                display,
+                similar.join(", ")
+            )
+        }
+    }
+}
+
+/// Ids are numbers in recent format versions and strings in older ones. Index keys are
+/// strings either way.
+fn id_key(id: &Value) -> Option<String> {
+    match id {
+        Value::Number(id) => Some(id.to_string()),
+        Value::String(id) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+/// An item's kind, e.g. `struct` or `function`, and the kind-specific part of the item.
+fn item_kind(item: &Value) -> (&str, &Value) {
+    match &item["inner"] {
+        Value::Object(inner) => inner
+            .iter()
+            .next()
+            .map_or(("unknown", &Value::Null), |(kind, inner)| {
+                (kind.as_str(), inner)
+            }),
+        Value::String(kind) => (kind.as_str(), &Value::Null),
+        _ => ("unknown", &Value::Null),
+    }
+}
+
+fn summary(docs: &str) -> Option<String> {
+    let paragraph = docs.split("\n\n").next()?.trim();
+    (!paragraph.is_empty()).then(|| paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Finds an item in rustdoc's HTML output. Modules are directories with an `index.html`, other
+/// items pages named `<kind>.<name>.html`, and members anchors like `method.<name>` on the page
+/// of their type.
+fn html_lookup(dir: &Path, display: String, path: &[&str]) -> Result<ItemDoc> {
+    let page = |dir: &Path, name: &str| -> Option<(String, PathBuf)> {
+        let module = dir.join(name).join("index.html");
+        if module.is_file() {
+            return Some(("module".to_string(), module));
+        }
+        std::fs::read_dir(dir).ok()?.find_map(|entry| {
+            let file_name = entry.ok()?.file_name().into_string().ok()?;
+            let (kind, rest) = file_name.split_once('.')?;
+            (rest == format!("{}.html", name)).then(|| (kind.to_string(), dir.join(&file_name)))
+        })
+    };
+
+    let found = match path {
+        [] => Some(("module".to_string(), dir.join("index.html"), None)),
+        [modules @ .., name] => {
+            let parent: PathBuf = modules.iter().fold(dir.to_path_buf(), |dir, m| dir.join(m));
+            match page(&parent, name) {
+                Some((kind, file)) => Some((kind, file, None)),
+                // A member of the type one level up.
+                None => match modules.split_last() {
+                    Some((owner, modules)) => {
+                        let parent: PathBuf =
+                            modules.iter().fold(dir.to_path_buf(), |dir, m| dir.join(m));
+                        page(&parent, owner)
+                            .map(|(_, file)| ("member".to_string(), file, Some(*name)))
+                    }
+                    None => None,
+                },
+            }
+        }
+    };
+    let Some((kind, file, member)) = found else {
+        bail!("No documentation page for '{}'", display);
+    };
+    let html = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let html = match member {
+        Some(member) => {
+            let anchor = [
+                "method",
+                "tymethod",
+                "variant",
+                "structfield",
+                "associatedtype",
+            ]
+            .iter()
+            .find_map(|prefix| html.find(&format!("id=\"{}.{}\"", prefix, member)));
+            let Some(start) = anchor else {
+                bail!("No public item '{}' found", display);
+            };
+            let start = html[..start].rfind('<').unwrap_or(start);
+            let rest = &html[start..];
+            // The member's section ends where the next one starts.
+            let end = rest[1..]
+                .find("<section id=\"")
+                .or_else(|| rest[1..].find("<details"))
+                .map_or(rest.len(), |end| end + 1);
+            &rest[..end]
+        }
+        None => match (html.find("<main"), html.find("</main>")) {
+            (Some(start), Some(end)) if start < end => &html[start..end],
+            _ => html.as_str(),
+        },
+    };
+
+    let mut text = html_text(html);
+    if let Some((cut, _)) = text.char_indices().nth(MAX_HTML_TEXT_LEN) {
+        text.truncate(cut);
+        text.push_str(" ...");
+    }
+    Ok(ItemDoc {
+        path: display,
+        kind,
+        signature: None,
+        docs: Some(text),
+        deprecation: None,
+        source: None,
+        members: Vec::new(),
+        trait_impls: Vec::new(),
+    })
+}
+
+fn html_text(html: &str) -> String {
+    static SKIPPED_REGEX: OnceLock<Regex> = OnceLock::new();
+    static TAG_REGEX: OnceLock<Regex> = OnceLock::new();
+    let skipped = SKIPPED_REGEX.get_or_init(|| {
+        Regex::new(r"(?s)<(script|style|button)[^>]*>.*?</(script|style|button)>").unwrap()
+    });
+    let tag = TAG_REGEX.get_or_init(|| Regex::new(r"<[^>]*>").unwrap());
+    let html = skipped.replace_all(html, " ");
+    let text = tag.replace_all(&html, " ");
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
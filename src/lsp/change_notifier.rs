@@ -1,14 +1,15 @@
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{path::PathBuf, time::Duration};
 
 use anyhow::Result;
-use async_lsp::{LanguageServer, ServerSocket};
+use lsp_types::notification::DidChangeWatchedFiles;
 use lsp_types::{DidChangeWatchedFilesParams, FileChangeType, FileEvent};
 use notify_debouncer_mini::{
     DebounceEventResult, DebouncedEvent, Debouncer, new_debouncer, notify::*,
 };
-use tokio::{runtime::Handle, sync::Mutex};
 use url::Url;
 
+use super::server_connection::ServerConnection;
+use crate::lsp::LspNotification;
 use crate::project::Project;
 
 #[derive(Debug)]
@@ -19,18 +20,21 @@ pub struct ChangeNotifier {
 
 impl ChangeNotifier {
     pub fn new(
-        server: Arc<Mutex<ServerSocket>>,
+        server: ServerConnection,
         project: &Project,
-        handle: Handle,
+        notifier: flume::Sender<LspNotification>,
     ) -> Result<Self> {
-        let handle_clone = handle.clone();
+        let root = project.root().clone();
         let target_path = project.root().join("target");
         let mut debouncer = new_debouncer(
             Duration::from_secs(2),
             move |res: DebounceEventResult| match res {
-                Ok(events) => events.iter().for_each(|e| {
-                    handle_event(e, server.clone(), handle_clone.clone(), target_path.clone())
-                }),
+                Ok(events) => {
+                    events
+                        .iter()
+                        .for_each(|e| handle_event(e, &server, &target_path));
+                    notify_manifest_changes(&events, &root, &target_path, &notifier);
+                }
                 Err(e) => tracing::error!("Error {:?}", e),
             },
         )?;
@@ -43,14 +47,34 @@ impl ChangeNotifier {
     }
 }
 
-fn handle_event(
-    event: &DebouncedEvent,
-    server: Arc<Mutex<ServerSocket>>,
-    handle: Handle,
-    target_path: PathBuf,
+/// Sends a single `ManifestChanged` for a batch of events that touched a manifest or lockfile.
+fn notify_manifest_changes(
+    events: &[DebouncedEvent],
+    root: &PathBuf,
+    target_path: &PathBuf,
+    notifier: &flume::Sender<LspNotification>,
 ) {
+    let manifest_changed = events.iter().any(|event| {
+        !event.path.starts_with(target_path)
+            && event
+                .path
+                .file_name()
+                .is_some_and(|name| name == "Cargo.toml" || name == "Cargo.lock")
+    });
+    if !manifest_changed {
+        return;
+    }
+    tracing::debug!("Manifest changed in {:?}", root);
+    if let Err(e) = notifier.send(LspNotification::ManifestChanged {
+        project: root.clone(),
+    }) {
+        tracing::error!("Failed to send manifest changed notification: {}", e);
+    }
+}
+
+fn handle_event(event: &DebouncedEvent, server: &ServerConnection, target_path: &PathBuf) {
     // Don't trigger lsp on target files. Otherwise it will trigger itself.
-    if event.path.starts_with(&target_path) {
+    if event.path.starts_with(target_path) {
         return;
     }
     tracing::trace!("Event {:?} for {:?}", event.kind, event.path);
@@ -61,15 +85,9 @@ fn handle_event(
             return;
         }
     };
-    handle.spawn(async move {
-        match server
-            .lock()
-            .await
-            .did_change_watched_files(DidChangeWatchedFilesParams {
-                changes: vec![FileEvent::new(url, FileChangeType::CHANGED)],
-            }) {
-            Ok(_) => (),
-            Err(e) => tracing::error!("Failed to send DidChangeWatchedFiles notification: {:?}", e),
-        }
-    });
+    if let Err(e) = server.notify::<DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent::new(url, FileChangeType::CHANGED)],
+    }) {
+        tracing::error!("Failed to send DidChangeWatchedFiles notification: {:?}", e);
+    }
 }
@@ -2,15 +2,18 @@ use std::ops::ControlFlow;
 use std::path::PathBuf;
 
 use super::Stop;
+use super::overlay::PublishedDiagnostics;
 use crate::lsp::{IndexingProgress, IndexingStage, LspNotification};
 use async_lsp::router::Router;
 use async_lsp::{LanguageClient, ResponseError};
+use lsp_types::notification::Notification;
 use lsp_types::{
     NumberOrString, ProgressParams, ProgressParamsValue, PublishDiagnosticsParams,
     ShowMessageParams, WorkDoneProgress,
 };
 use regex::Regex;
-use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
 
 // Old and new token names.
 const RA_INDEXING_TOKENS: &[&str] = &[
@@ -23,6 +26,7 @@ pub struct ClientState {
     project: PathBuf,
     indexed_tx: Option<flume::Sender<()>>,
     notifier: flume::Sender<LspNotification>,
+    diagnostics: Arc<PublishedDiagnostics>,
 }
 
 impl LanguageClient for ClientState {
@@ -69,7 +73,8 @@ impl LanguageClient for ClientState {
         ControlFlow::Continue(())
     }
 
-    fn publish_diagnostics(&mut self, _: PublishDiagnosticsParams) -> Self::NotifyResult {
+    fn publish_diagnostics(&mut self, params: PublishDiagnosticsParams) -> Self::NotifyResult {
+        self.diagnostics.publish(params);
         ControlFlow::Continue(())
     }
 
@@ -139,17 +144,39 @@ impl ClientState {
         })
     }
 
+    fn server_status(&mut self, params: ServerStatusParams) -> ControlFlow<async_lsp::Result<()>> {
+        if params.health != "ok" {
+            tracing::warn!(
+                "rust-analyzer status {}: {:?}",
+                params.health,
+                params.message
+            );
+        }
+        if let Err(e) = self.notifier.send(LspNotification::Status {
+            project: self.project.clone(),
+            quiescent: params.quiescent,
+            message: params.message,
+        }) {
+            tracing::error!("Failed to send server status notification: {}", e);
+        }
+        ControlFlow::Continue(())
+    }
+
     pub fn new_router(
         indexed_tx: flume::Sender<()>,
         notifier: flume::Sender<LspNotification>,
+        diagnostics: Arc<PublishedDiagnostics>,
         project: PathBuf,
     ) -> Router<Self> {
         let mut router = Router::from_language_client(ClientState {
             indexed_tx: Some(indexed_tx),
             notifier,
+            diagnostics,
             project,
         });
-        router.event(Self::on_stop);
+        router
+            .event(Self::on_stop)
+            .notification::<ServerStatus>(Self::server_status);
         router
     }
 
@@ -157,3 +184,19 @@ impl ClientState {
         ControlFlow::Break(Ok(()))
     }
 }
+
+/// rust-analyzer extension reporting whether the workspace is loaded and background work is done.
+enum ServerStatus {}
+
+impl Notification for ServerStatus {
+    type Params = ServerStatusParams;
+    const METHOD: &'static str = "experimental/serverStatus";
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ServerStatusParams {
+    /// `ok`, `warning` or `error`.
+    health: String,
+    quiescent: bool,
+    message: Option<String>,
+}
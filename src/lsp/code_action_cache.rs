@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use lsp_types::{
+    CodeActionOrCommand, CodeActionResponse, DocumentChangeOperation, DocumentChanges, Range, Url,
+    WorkspaceEdit,
+};
+
+/// Code actions per file and range, valid as long as the file's contents hash the same.
+/// Edits to one file therefore don't cost a round trip for the ranges of another. Only actions
+/// that edit nothing but their own file are cached, see [`edits_only`].
+#[derive(Debug, Default)]
+pub(super) struct CodeActionCache(Mutex<HashMap<PathBuf, CachedFile>>);
+
+#[derive(Debug)]
+struct CachedFile {
+    content_hash: u64,
+    actions: HashMap<Range, Option<CodeActionResponse>>,
+}
+
+impl CodeActionCache {
+    pub(super) fn get(
+        &self,
+        file: &Path,
+        content_hash: u64,
+        range: Range,
+    ) -> Option<Option<CodeActionResponse>> {
+        let files = self.0.lock().unwrap();
+        let cached = files.get(file)?;
+        if cached.content_hash != content_hash {
+            return None;
+        }
+        cached.actions.get(&range).cloned()
+    }
+
+    pub(super) fn insert(
+        &self,
+        file: &Path,
+        content_hash: u64,
+        range: Range,
+        actions: Option<CodeActionResponse>,
+    ) {
+        let mut files = self.0.lock().unwrap();
+        let cached = files
+            .entry(file.to_path_buf())
+            .or_insert_with(|| CachedFile {
+                content_hash,
+                actions: HashMap::new(),
+            });
+        // Whatever was cached for other contents of the file is stale now.
+        if cached.content_hash != content_hash {
+            cached.content_hash = content_hash;
+            cached.actions.clear();
+        }
+        cached.actions.insert(range, actions);
+    }
+
+    pub(super) fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+pub(super) fn content_hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether every edit of `actions` is to `uri`. Edits to other files (or creating, renaming and
+/// deleting files) depend on more than the contents of `uri`, so such actions can't be reused.
+pub(super) fn edits_only(actions: &Option<CodeActionResponse>, uri: &Url) -> bool {
+    actions.iter().flatten().all(|action| match action {
+        CodeActionOrCommand::CodeAction(action) => action
+            .edit
+            .as_ref()
+            .is_none_or(|edit| workspace_edit_only(edit, uri)),
+        CodeActionOrCommand::Command(_) => true,
+    })
+}
+
+fn workspace_edit_only(edit: &WorkspaceEdit, uri: &Url) -> bool {
+    let changes_only = edit
+        .changes
+        .as_ref()
+        .is_none_or(|changes| changes.keys().all(|changed| changed == uri));
+    let document_changes_only = match &edit.document_changes {
+        None => true,
+        Some(DocumentChanges::Edits(edits)) => {
+            edits.iter().all(|edit| edit.text_document.uri == *uri)
+        }
+        Some(DocumentChanges::Operations(operations)) => {
+            operations.iter().all(|operation| match operation {
+                DocumentChangeOperation::Edit(edit) => edit.text_document.uri == *uri,
+                DocumentChangeOperation::Op(_) => false,
+            })
+        }
+    };
+    changes_only && document_changes_only
+}
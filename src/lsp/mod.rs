@@ -1,12 +1,19 @@
 mod change_notifier;
 mod client_state;
+mod code_action_cache;
+mod overlay;
+mod readiness;
+mod request_stats;
 mod rust_analyzer_lsp;
+mod server_connection;
 mod utils;
 
 pub(super) struct Stop;
 
 use std::path::PathBuf;
 
+pub use overlay::Overlay;
+pub use readiness::Readiness;
 pub use rust_analyzer_lsp::RustAnalyzerLsp;
 pub use utils::*;
 
@@ -17,6 +24,14 @@ pub enum LspNotification {
         is_indexing: bool,
         progress: Option<IndexingProgress>,
     },
+    /// A `Cargo.toml` or `Cargo.lock` inside the project changed.
+    ManifestChanged { project: PathBuf },
+    /// rust-analyzer's `experimental/serverStatus`.
+    Status {
+        project: PathBuf,
+        quiescent: bool,
+        message: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone)]
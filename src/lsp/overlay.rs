@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use lsp_types::notification::{DidCloseTextDocument, DidOpenTextDocument};
+use lsp_types::{
+    Diagnostic, DidCloseTextDocumentParams, DidOpenTextDocumentParams, PublishDiagnosticsParams,
+    TextDocumentIdentifier, TextDocumentItem, Url,
+};
+use tokio::sync::{Notify, OwnedMutexGuard};
+
+use super::server_connection::ServerConnection;
+
+/// Documents whose unsaved contents rust-analyzer analyses instead of the files on disk.
+#[derive(Debug, Default)]
+pub(super) struct OpenDocuments(Mutex<HashMap<Url, OpenDocument>>);
+
+#[derive(Debug, Default)]
+struct OpenDocument {
+    version: i32,
+    /// Held by the document's overlay. The entry stays after it closes to remember the version.
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl OpenDocuments {
+    /// Opens `uri` with `text`. A document has one overlay at a time, so if another call overlays
+    /// it already, this waits until that overlay is dropped rather than changing its contents
+    /// under it. Versions keep counting across reopenings, so diagnostics published for an
+    /// earlier overlay never pass for a later one.
+    pub(super) async fn open(
+        &self,
+        server: &ServerConnection,
+        uri: Url,
+        text: String,
+    ) -> Result<Overlay> {
+        let lock = self
+            .0
+            .lock()
+            .unwrap()
+            .entry(uri.clone())
+            .or_default()
+            .lock
+            .clone();
+        let guard = lock.lock_owned().await;
+        let version = {
+            let mut documents = self.0.lock().unwrap();
+            let document = documents.entry(uri.clone()).or_default();
+            document.version += 1;
+            document.version
+        };
+        server
+            .notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "rust".into(),
+                    version,
+                    text,
+                },
+            })
+            .context("Sending DidOpen notification failed")?;
+        Ok(Overlay {
+            uri,
+            version,
+            server: server.clone(),
+            _guard: guard,
+        })
+    }
+}
+
+/// Unsaved contents of one file, visible to rust-analyzer until this is dropped.
+/// Dropping also happens when a tool call is cancelled, so overlays never outlive their call.
+#[derive(Debug)]
+pub struct Overlay {
+    uri: Url,
+    version: i32,
+    server: ServerConnection,
+    /// Released after the DidClose is sent, so the next overlay of the file opens it afresh.
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Overlay {
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        if let Err(e) = self
+            .server
+            .notify::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier {
+                    uri: self.uri.clone(),
+                },
+            })
+        {
+            tracing::warn!("Failed to send DidClose for {}: {:?}", self.uri, e);
+        }
+    }
+}
+
+/// The latest diagnostics rust-analyzer published for each file.
+#[derive(Debug, Default)]
+pub(super) struct PublishedDiagnostics {
+    latest: Mutex<HashMap<Url, PublishDiagnosticsParams>>,
+    updated: Notify,
+}
+
+impl PublishedDiagnostics {
+    pub(super) fn publish(&self, params: PublishDiagnosticsParams) {
+        self.latest
+            .lock()
+            .unwrap()
+            .insert(params.uri.clone(), params);
+        self.updated.notify_waiters();
+    }
+
+    /// Waits until diagnostics for `version` (or later) of `uri` are published. rust-analyzer
+    /// skips publishing when nothing changed, so after `timeout` this settles for the latest
+    /// diagnostics of the file and reports `false`.
+    pub(super) async fn wait_for(
+        &self,
+        uri: &Url,
+        version: i32,
+        timeout: Duration,
+    ) -> (Vec<Diagnostic>, bool) {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let updated = self.updated.notified();
+            tokio::pin!(updated);
+            // Register before looking, so a publication in between isn't missed.
+            updated.as_mut().enable();
+            let latest = self.latest.lock().unwrap().get(uri).cloned();
+            if let Some(params) = latest
+                && params.version.is_some_and(|published| published >= version)
+            {
+                return (params.diagnostics, true);
+            }
+            if tokio::time::timeout_at(deadline, updated).await.is_err() {
+                let latest = self.latest.lock().unwrap().get(uri).cloned();
+                let diagnostics = latest.map(|params| params.diagnostics).unwrap_or_default();
+                return (diagnostics, false);
+            }
+        }
+    }
+}
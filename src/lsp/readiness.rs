@@ -0,0 +1,131 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use super::{IndexingProgress, IndexingStage};
+
+/// What is known about whether rust-analyzer can give complete answers for a project.
+#[derive(Debug, Clone)]
+pub struct ReadinessState {
+    pub is_indexing: bool,
+    /// Latest progress report of the running indexing stage.
+    pub progress: Option<IndexingProgress>,
+    /// rust-analyzer's `experimental/serverStatus` quiescent flag: the workspace is loaded and no
+    /// background work is pending. `None` until the first status arrives.
+    pub quiescent: Option<bool>,
+    pub message: Option<String>,
+}
+
+impl ReadinessState {
+    /// Quiescence is authoritative; servers that don't send statuses fall back to the progress
+    /// notifications, which end between indexing stages and are therefore less reliable.
+    pub fn is_ready(&self) -> bool {
+        self.quiescent.unwrap_or(!self.is_indexing)
+    }
+
+    /// Overall progress of the running stage in percent, if rust-analyzer reported one.
+    fn percentage(&self) -> Option<u32> {
+        let progress = self.progress.as_ref()?;
+        match (progress.current_count, progress.total_count) {
+            (Some(current), Some(total)) if total > 0 => Some(current * 100 / total),
+            _ => progress.percentage.map(|percentage| percentage as u32),
+        }
+    }
+}
+
+/// Readiness of one project's rust-analyzer, fed by its progress and status notifications.
+#[derive(Debug)]
+pub struct Readiness(watch::Sender<ReadinessState>);
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self(watch::Sender::new(ReadinessState {
+            is_indexing: true,
+            progress: None,
+            quiescent: None,
+            message: None,
+        }))
+    }
+}
+
+impl Readiness {
+    pub fn state(&self) -> ReadinessState {
+        self.0.borrow().clone()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0.borrow().is_ready()
+    }
+
+    pub fn update_indexing(&self, is_indexing: bool, progress: Option<IndexingProgress>) {
+        self.0.send_modify(|state| {
+            state.is_indexing = is_indexing;
+            state.progress = progress;
+        });
+    }
+
+    pub fn update_status(&self, quiescent: bool, message: Option<String>) {
+        self.0.send_modify(|state| {
+            state.quiescent = Some(quiescent);
+            state.message = message;
+        });
+    }
+
+    /// Waits up to `timeout` for the project to become ready.
+    pub async fn wait(&self, timeout: Duration) -> Result<(), NotReady> {
+        let mut receiver = self.0.subscribe();
+        match tokio::time::timeout(timeout, receiver.wait_for(ReadinessState::is_ready)).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err(NotReady::from(&self.state())),
+        }
+    }
+}
+
+/// Returned instead of an answer while rust-analyzer is still loading or indexing the project,
+/// since results would be empty or partial.
+#[derive(Debug, Clone, Serialize)]
+pub struct NotReady {
+    pub stage: Option<String>,
+    pub percentage: Option<u32>,
+    pub current_crate: Option<String>,
+    pub message: Option<String>,
+}
+
+impl From<&ReadinessState> for NotReady {
+    fn from(state: &ReadinessState) -> Self {
+        let progress = state.progress.as_ref();
+        Self {
+            stage: progress.map(|progress| match &progress.stage {
+                IndexingStage::Building => "building".to_string(),
+                IndexingStage::CachePriming => "cache_priming".to_string(),
+                IndexingStage::Indexing => "indexing".to_string(),
+                IndexingStage::Unknown(stage) => stage.clone(),
+            }),
+            percentage: state.percentage(),
+            current_crate: progress.and_then(|progress| progress.current_crate.clone()),
+            message: state.message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for NotReady {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rust-analyzer is still indexing the project")?;
+        if let Some(stage) = &self.stage {
+            write!(f, " ({}", stage)?;
+            if let Some(percentage) = self.percentage {
+                write!(f, ", {}% done", percentage)?;
+            }
+            if let Some(current_crate) = &self.current_crate {
+                write!(f, ", at {}", current_crate)?;
+            }
+            write!(f, ")")?;
+        }
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        write!(f, ". Results would be incomplete; try again shortly.")
+    }
+}
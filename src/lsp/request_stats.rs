@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+/// Requests taking longer than this are logged as warnings.
+const SLOW_REQUEST: Duration = Duration::from_secs(5);
+
+/// Timing of the requests sent to one rust-analyzer, per LSP method.
+#[derive(Debug, Default)]
+pub(super) struct RequestStats(Mutex<BTreeMap<&'static str, MethodStats>>);
+
+#[derive(Debug, Default)]
+struct MethodStats {
+    in_flight: usize,
+    completed: usize,
+    failed: usize,
+    cancelled: usize,
+    total: Duration,
+    max: Duration,
+    last: Option<Duration>,
+}
+
+/// Summary of the requests made for one LSP method.
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodTiming {
+    pub method: &'static str,
+    /// Requests currently awaiting a response.
+    pub in_flight: usize,
+    pub completed: usize,
+    pub failed: usize,
+    /// Requests dropped before their response arrived, e.g. by a timed-out tool call.
+    pub cancelled: usize,
+    /// Mean and maximum duration of completed and failed requests.
+    pub mean_ms: u128,
+    pub max_ms: u128,
+    pub last_ms: Option<u128>,
+}
+
+impl RequestStats {
+    /// Starts timing request `id`. The returned timer records it as cancelled unless finished.
+    pub(super) fn start(&self, method: &'static str, id: i32) -> RequestTimer<'_> {
+        self.0.lock().unwrap().entry(method).or_default().in_flight += 1;
+        RequestTimer {
+            stats: self,
+            method,
+            id,
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    pub(super) fn snapshot(&self) -> Vec<MethodTiming> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(method, stats)| {
+                let answered = (stats.completed + stats.failed) as u32;
+                MethodTiming {
+                    method,
+                    in_flight: stats.in_flight,
+                    completed: stats.completed,
+                    failed: stats.failed,
+                    cancelled: stats.cancelled,
+                    mean_ms: stats
+                        .total
+                        .checked_div(answered)
+                        .unwrap_or_default()
+                        .as_millis(),
+                    max_ms: stats.max.as_millis(),
+                    last_ms: stats.last.map(|last| last.as_millis()),
+                }
+            })
+            .collect()
+    }
+
+    fn record(&self, method: &'static str, elapsed: Duration, outcome: Outcome) {
+        let mut methods = self.0.lock().unwrap();
+        let stats = methods.entry(method).or_default();
+        stats.in_flight -= 1;
+        match outcome {
+            Outcome::Completed => stats.completed += 1,
+            Outcome::Failed => stats.failed += 1,
+            Outcome::Cancelled => {
+                stats.cancelled += 1;
+                return;
+            }
+        }
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+        stats.last = Some(elapsed);
+    }
+}
+
+enum Outcome {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+pub(super) struct RequestTimer<'a> {
+    stats: &'a RequestStats,
+    method: &'static str,
+    id: i32,
+    started: Instant,
+    finished: bool,
+}
+
+impl RequestTimer<'_> {
+    pub(super) fn finish(mut self, success: bool) {
+        self.finished = true;
+        let elapsed = self.started.elapsed();
+        if elapsed >= SLOW_REQUEST {
+            warn!(
+                "LSP request {} ({}) took {:?}",
+                self.id, self.method, elapsed
+            );
+        } else {
+            debug!(
+                "LSP request {} ({}) took {:?}",
+                self.id, self.method, elapsed
+            );
+        }
+        let outcome = if success {
+            Outcome::Completed
+        } else {
+            Outcome::Failed
+        };
+        self.stats.record(self.method, elapsed, outcome);
+    }
+}
+
+impl Drop for RequestTimer<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let elapsed = self.started.elapsed();
+        debug!(
+            "LSP request {} ({}) cancelled after {:?}",
+            self.id, self.method, elapsed
+        );
+        self.stats.record(self.method, elapsed, Outcome::Cancelled);
+    }
+}
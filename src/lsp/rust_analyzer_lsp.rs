@@ -1,23 +1,33 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use async_lsp::concurrency::ConcurrencyLayer;
 use async_lsp::panic::CatchUnwindLayer;
 use async_lsp::server::LifecycleLayer;
 use async_lsp::tracing::TracingLayer;
-use async_lsp::{LanguageServer, ServerSocket};
-use lsp_types::request::{CodeActionRequest, Rename, WorkspaceSymbolRequest};
+use lsp_types::notification::{Cancel, Exit, Initialized};
+use lsp_types::request::{
+    CodeActionRequest, Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Initialize,
+    InlayHintRequest, References, Rename, Request, ResolveCompletionItem, Shutdown,
+    SignatureHelpRequest, WorkspaceSymbolRequest,
+};
 use lsp_types::{
-    ClientCapabilities, CodeActionClientCapabilities, CodeActionContext, CodeActionLiteralSupport,
-    CodeActionParams, CodeActionResponse, DidOpenTextDocumentParams,
-    DocumentSymbolClientCapabilities, Hover, HoverClientCapabilities, HoverParams,
-    InitializeParams, InitializedParams, Location, MarkupKind, Position, Range, ReferenceContext,
-    ReferenceParams, RenameParams, TextDocumentClientCapabilities, TextDocumentIdentifier,
-    TextDocumentItem, TextDocumentPositionParams, Url, WindowClientCapabilities,
-    WorkDoneProgressParams, WorkspaceEdit, WorkspaceEditClientCapabilities, WorkspaceFolder,
-    WorkspaceSymbolClientCapabilities, WorkspaceSymbolParams,
+    CancelParams, ClientCapabilities, CodeActionClientCapabilities, CodeActionContext,
+    CodeActionLiteralSupport, CodeActionParams, CodeActionResponse, CompletionClientCapabilities,
+    CompletionItem, CompletionItemCapability, CompletionItemCapabilityResolveSupport,
+    CompletionParams, CompletionResponse, Diagnostic, DocumentSymbolClientCapabilities,
+    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    Hover, HoverClientCapabilities, HoverParams, InitializeParams, InitializedParams, InlayHint,
+    InlayHintClientCapabilities, InlayHintParams, Location, MarkupKind, NumberOrString, OneOf,
+    ParameterInformationSettings, Position, Range, ReferenceContext, ReferenceParams, RenameParams,
+    SignatureHelp, SignatureHelpClientCapabilities, SignatureHelpParams,
+    SignatureInformationSettings, TextDocumentClientCapabilities, TextDocumentIdentifier,
+    TextDocumentPositionParams, Url, WindowClientCapabilities, WorkDoneProgressParams,
+    WorkspaceEdit, WorkspaceEditClientCapabilities, WorkspaceFolder,
+    WorkspaceSymbolClientCapabilities, WorkspaceSymbolParams, WorkspaceSymbolResponse,
 };
 use serde_json::json;
 use tokio::sync::Mutex;
@@ -27,6 +37,10 @@ use tracing::{debug, info};
 
 use super::change_notifier::ChangeNotifier;
 use super::client_state::ClientState;
+use super::code_action_cache::{CodeActionCache, content_hash, edits_only};
+use super::overlay::{OpenDocuments, Overlay, PublishedDiagnostics};
+use super::request_stats::{MethodTiming, RequestStats};
+use super::server_connection::ServerConnection;
 use crate::lsp::LspNotification;
 use crate::project::Project;
 use flume::Sender;
@@ -34,17 +48,31 @@ use flume::Sender;
 #[derive(Debug)]
 pub struct RustAnalyzerLsp {
     project: Project,
-    server: Arc<Mutex<ServerSocket>>,
+    /// Directories of ignored crates. Symbols and references inside them are filtered out.
+    excluded_dirs: Vec<PathBuf>,
+    server: ServerConnection,
+    request_stats: RequestStats,
+    code_action_cache: CodeActionCache,
     #[allow(dead_code)] // Keep the handle to ensure the mainloop runs
     mainloop_handle: Mutex<Option<JoinHandle<()>>>,
+    #[allow(dead_code)] // Keep the receiver so indexing completion signals can be sent
     indexed_rx: Mutex<flume::Receiver<()>>,
+    documents: OpenDocuments,
+    diagnostics: Arc<PublishedDiagnostics>,
     #[allow(dead_code)] // Keep the handle to ensure the change notifier runs
     change_notifier: ChangeNotifier,
 }
 
 impl RustAnalyzerLsp {
-    pub async fn new(project: &Project, notifier: Sender<LspNotification>) -> Result<Self> {
+    pub async fn new(
+        project: &Project,
+        excluded_dirs: Vec<PathBuf>,
+        notifier: Sender<LspNotification>,
+    ) -> Result<Self> {
         let (indexed_tx, indexed_rx) = flume::unbounded();
+        let manifest_notifier = notifier.clone();
+        let diagnostics = Arc::new(PublishedDiagnostics::default());
+        let published_diagnostics = diagnostics.clone();
         let (mainloop, server) = async_lsp::MainLoop::new_client(|_server| {
             ServiceBuilder::new()
                 .layer(TracingLayer::default())
@@ -54,6 +82,7 @@ impl RustAnalyzerLsp {
                 .service(ClientState::new_router(
                     indexed_tx,
                     notifier,
+                    published_diagnostics,
                     project.root().to_path_buf(),
                 ))
         });
@@ -69,33 +98,35 @@ impl RustAnalyzerLsp {
         let stdout = process.stdout.context("Failed to get stdout")?;
         let stdin = process.stdin.context("Failed to get stdin")?;
 
+        let (connection, mainloop_input, mainloop_output) = ServerConnection::new(stdout, stdin);
         let mainloop_handle = tokio::spawn(async move {
-            match mainloop.run_buffered(stdout, stdin).await {
+            // The mainloop stops once every socket to it is dropped, though we send through
+            // `connection` instead.
+            let _server = server;
+            match mainloop.run_buffered(mainloop_input, mainloop_output).await {
                 Ok(()) => debug!("LSP mainloop finished gracefully."),
                 Err(e) => tracing::error!("LSP mainloop finished with error: {}", e),
             }
         });
 
-        let server = Arc::new(Mutex::new(server));
-
-        // Get the current runtime handle
-        let handle = tokio::runtime::Handle::current();
-        let change_notifier = ChangeNotifier::new(server.clone(), project, handle)?;
+        let change_notifier = ChangeNotifier::new(connection.clone(), project, manifest_notifier)?;
 
         let client = Self {
             project: project.clone(),
-            server,
+            excluded_dirs,
+            server: connection,
+            request_stats: RequestStats::default(),
+            code_action_cache: CodeActionCache::default(),
             mainloop_handle: Mutex::new(Some(mainloop_handle)),
             indexed_rx: Mutex::new(indexed_rx),
+            documents: OpenDocuments::default(),
+            diagnostics,
             change_notifier,
         };
 
         // Initialize.
         let init_ret = client
-            .server
-            .lock()
-            .await
-            .initialize(InitializeParams {
+            .request::<Initialize>(InitializeParams {
                 workspace_folders: Some(vec![WorkspaceFolder {
                     uri: project.uri()?,
                     name: "root".into(),
@@ -126,6 +157,34 @@ impl RustAnalyzerLsp {
                             content_format: Some(vec![MarkupKind::Markdown]),
                             ..HoverClientCapabilities::default()
                         }),
+                        completion: Some(CompletionClientCapabilities {
+                            completion_item: Some(CompletionItemCapability {
+                                snippet_support: Some(false),
+                                documentation_format: Some(vec![MarkupKind::Markdown]),
+                                label_details_support: Some(true),
+                                // Documentation is fetched only for the items we return.
+                                resolve_support: Some(CompletionItemCapabilityResolveSupport {
+                                    properties: vec!["documentation".into(), "detail".into()],
+                                }),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }),
+                        signature_help: Some(SignatureHelpClientCapabilities {
+                            signature_information: Some(SignatureInformationSettings {
+                                documentation_format: Some(vec![MarkupKind::Markdown]),
+                                parameter_information: Some(ParameterInformationSettings {
+                                    label_offset_support: Some(true),
+                                }),
+                                active_parameter_support: Some(true),
+                            }),
+                            ..Default::default()
+                        }),
+                        inlay_hint: Some(InlayHintClientCapabilities {
+                            dynamic_registration: Some(false),
+                            // Without resolve support the labels arrive complete.
+                            resolve_support: None,
+                        }),
                         code_action: Some(CodeActionClientCapabilities {
                             code_action_literal_support: Some(CodeActionLiteralSupport {
                                 code_action_kind: lsp_types::CodeActionKindLiteralSupport {
@@ -154,10 +213,13 @@ impl RustAnalyzerLsp {
                         ..TextDocumentClientCapabilities::default()
                     }),
                     experimental: Some(json!({
-                        "hoverActions": true
+                        "hoverActions": true,
+                        // Tells us when the workspace is fully loaded, see `Readiness`.
+                        "serverStatusNotification": true
                     })),
                     ..ClientCapabilities::default()
                 },
+                initialization_options: client.initialization_options(),
                 ..InitializeParams::default()
             })
             .await
@@ -167,9 +229,7 @@ impl RustAnalyzerLsp {
 
         client
             .server
-            .lock()
-            .await
-            .initialized(InitializedParams {})
+            .notify::<Initialized>(InitializedParams {})
             .context("Sending Initialized notification failed")?;
 
         info!("Waiting for rust-analyzer indexing...");
@@ -177,17 +237,68 @@ impl RustAnalyzerLsp {
         Ok(client)
     }
 
+    /// rust-analyzer settings passed at startup. Ignored crates go to `files.excludeDirs` so they
+    /// are neither indexed nor watched. Inlay hints are tuned for reading code rather than
+    /// editing it: nothing is truncated and closure return types are always shown.
+    fn initialization_options(&self) -> Option<serde_json::Value> {
+        let exclude_dirs: Vec<String> = self
+            .excluded_dirs
+            .iter()
+            .map(|dir| {
+                dir.strip_prefix(self.project.root())
+                    .unwrap_or(dir)
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        Some(json!({
+            "files": {
+                "excludeDirs": exclude_dirs
+            },
+            "inlayHints": {
+                "maxLength": null,
+                "typeHints": { "enable": true },
+                "parameterHints": { "enable": true },
+                "chainingHints": { "enable": true },
+                "closureReturnTypeHints": { "enable": "always" },
+                "lifetimeElisionHints": { "enable": "skip_trivial", "useParameterNames": true }
+            }
+        }))
+    }
+
+    fn is_excluded(&self, uri: &Url) -> bool {
+        uri.to_file_path()
+            .is_ok_and(|path| self.excluded_dirs.iter().any(|dir| path.starts_with(dir)))
+    }
+
+    /// Sends a request and waits for its response. Any number of requests can be in flight at
+    /// once; their timings are recorded in `request_stats`.
+    /// If the returned future is dropped before the response arrives (because the tool call was
+    /// cancelled or timed out), rust-analyzer is told to stop working on it via `$/cancelRequest`.
+    async fn request<R: Request>(&self, params: R::Params) -> Result<R::Result> {
+        let request = self.server.request::<R>(params)?;
+        let timer = self.request_stats.start(R::METHOD, request.id());
+        let cancel_guard = CancelOnDrop {
+            server: self.server.clone(),
+            id: Some(request.id()),
+        };
+        let result = request.response().await;
+        cancel_guard.disarm();
+        timer.finish(result.is_ok());
+        Ok(result?)
+    }
+
+    /// Timing of the requests made so far, per LSP method.
+    pub fn request_timings(&self) -> Vec<MethodTiming> {
+        self.request_stats.snapshot()
+    }
+
     pub async fn shutdown(&self) -> Result<()> {
-        self.server
-            .lock()
-            .await
-            .shutdown(())
+        self.request::<Shutdown>(())
             .await
             .context("Sending Shutdown request failed")?;
         self.server
-            .lock()
-            .await
-            .exit(())
+            .notify::<Exit>(())
             .context("Sending Exit notification failed")?;
 
         // Wait for the mainloop to finish. This implicitly waits for the process to exit.
@@ -200,30 +311,6 @@ impl RustAnalyzerLsp {
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub async fn open_file(&self, relative_path: impl AsRef<Path>, text: String) -> Result<()> {
-        let uri = self.project.file_uri(relative_path)?;
-        self.server
-            .lock()
-            .await
-            .did_open(DidOpenTextDocumentParams {
-                text_document: TextDocumentItem {
-                    uri: uri.clone(),
-                    language_id: "rust".into(), // Assuming Rust, could be made generic
-                    version: 0,                 // Start with version 0
-                    text,
-                },
-            })
-            .context("Sending DidOpen notification failed")?;
-        self.indexed_rx
-            .lock()
-            .await
-            .recv_async()
-            .await
-            .context("Failed waiting for index")?;
-        Ok(())
-    }
-
     pub async fn hover(
         &self,
         file_path: impl AsRef<Path>,
@@ -231,18 +318,45 @@ impl RustAnalyzerLsp {
     ) -> Result<Option<Hover>> {
         let uri = Url::from_file_path(file_path.as_ref())
             .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
-        self.server
-            .lock()
-            .await
-            .hover(HoverParams {
+        self.request::<HoverRequest>(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        })
+        .await
+        .context("Hover request failed")
+    }
+
+    /// Locations of the definition of whatever is at `position`.
+    pub async fn definition(
+        &self,
+        file_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Vec<Location>> {
+        let uri = Url::from_file_path(file_path.as_ref())
+            .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
+        let definition = self
+            .request::<GotoDefinition>(GotoDefinitionParams {
                 text_document_position_params: TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier { uri },
                     position,
                 },
-                work_done_progress_params: WorkDoneProgressParams::default(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
             })
             .await
-            .context("Hover request failed")
+            .context("Definition request failed")?;
+        Ok(match definition {
+            Some(GotoDefinitionResponse::Scalar(location)) => vec![location],
+            Some(GotoDefinitionResponse::Array(locations)) => locations,
+            Some(GotoDefinitionResponse::Link(links)) => links
+                .into_iter()
+                .map(|link| Location::new(link.target_uri, link.target_selection_range))
+                .collect(),
+            None => Vec::new(),
+        })
     }
 
     pub async fn find_references(
@@ -252,10 +366,8 @@ impl RustAnalyzerLsp {
     ) -> Result<Option<Vec<Location>>> {
         let uri = Url::from_file_path(file_path.as_ref())
             .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
-        self.server
-            .lock()
-            .await
-            .references(ReferenceParams {
+        let references = self
+            .request::<References>(ReferenceParams {
                 text_document_position: TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier { uri },
                     position,
@@ -267,44 +379,109 @@ impl RustAnalyzerLsp {
                 },
             })
             .await
-            .context("References request failed")
+            .context("References request failed")?;
+        Ok(references.map(|locations| {
+            locations
+                .into_iter()
+                .filter(|location| !self.is_excluded(&location.uri))
+                .collect()
+        }))
     }
 
     pub async fn workspace_symbols(
         &self,
         query: String,
     ) -> Result<Option<lsp_types::WorkspaceSymbolResponse>> {
-        self.server
-            .lock()
-            .await
+        let symbols = self
             .request::<WorkspaceSymbolRequest>(WorkspaceSymbolParams {
                 query,
                 work_done_progress_params: Default::default(),
                 partial_result_params: Default::default(),
             })
             .await
-            .context("Workspace symbols request failed")
+            .context("Workspace symbols request failed")?;
+        Ok(symbols.map(|symbols| match symbols {
+            WorkspaceSymbolResponse::Flat(symbols) => WorkspaceSymbolResponse::Flat(
+                symbols
+                    .into_iter()
+                    .filter(|symbol| !self.is_excluded(&symbol.location.uri))
+                    .collect(),
+            ),
+            WorkspaceSymbolResponse::Nested(symbols) => WorkspaceSymbolResponse::Nested(
+                symbols
+                    .into_iter()
+                    .filter(|symbol| match &symbol.location {
+                        OneOf::Left(location) => !self.is_excluded(&location.uri),
+                        OneOf::Right(location) => !self.is_excluded(&location.uri),
+                    })
+                    .collect(),
+            ),
+        }))
     }
 
-    pub async fn code_actions(
+    /// Symbols declared in a file, flattened with their container names.
+    pub async fn document_symbols(
         &self,
         file_path: impl AsRef<Path>,
-        range: Range,
-    ) -> Result<Option<CodeActionResponse>> {
+    ) -> Result<Vec<lsp_types::SymbolInformation>> {
         let uri = Url::from_file_path(file_path.as_ref())
             .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
-        self.server
-            .lock()
-            .await
-            .request::<CodeActionRequest>(CodeActionParams {
+        let symbols = self
+            .request::<DocumentSymbolRequest>(DocumentSymbolParams {
                 text_document: TextDocumentIdentifier { uri },
-                range,
-                context: CodeActionContext::default(),
                 work_done_progress_params: Default::default(),
                 partial_result_params: Default::default(),
             })
             .await
-            .context("Code action request failed")
+            .context("Document symbols request failed")?;
+        Ok(match symbols {
+            Some(DocumentSymbolResponse::Flat(symbols)) => symbols,
+            // We ask for flat symbols, but a server may still answer with a tree.
+            Some(DocumentSymbolResponse::Nested(_)) | None => Vec::new(),
+        })
+    }
+
+    pub async fn code_actions(
+        &self,
+        file_path: impl AsRef<Path>,
+        range: Range,
+    ) -> Result<Option<CodeActionResponse>> {
+        let uri = Url::from_file_path(file_path.as_ref())
+            .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
+        self.request::<CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier { uri },
+            range,
+            context: CodeActionContext::default(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .context("Code action request failed")
+    }
+
+    /// Like `code_actions`, but answered from the cache while the file's contents are unchanged.
+    /// Actions that edit other files are always asked for afresh.
+    pub async fn cached_code_actions(
+        &self,
+        file_path: impl AsRef<Path>,
+        range: Range,
+    ) -> Result<Option<CodeActionResponse>> {
+        let file_path = file_path.as_ref();
+        let content = tokio::fs::read(file_path)
+            .await
+            .with_context(|| format!("Failed to read {}", file_path.display()))?;
+        let content_hash = content_hash(&content);
+        if let Some(actions) = self.code_action_cache.get(file_path, content_hash, range) {
+            return Ok(actions);
+        }
+        let actions = self.code_actions(file_path, range).await?;
+        let uri = Url::from_file_path(file_path)
+            .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
+        if edits_only(&actions, &uri) {
+            self.code_action_cache
+                .insert(file_path, content_hash, range, actions.clone());
+        }
+        Ok(actions)
     }
 
     pub async fn rename(
@@ -315,18 +492,155 @@ impl RustAnalyzerLsp {
     ) -> Result<Option<WorkspaceEdit>> {
         let uri = Url::from_file_path(file_path.as_ref())
             .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
-        self.server
-            .lock()
+        self.request::<Rename>(RenameParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            new_name,
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .context("Rename request failed")
+    }
+
+    /// Inlay hints (inferred types, parameter names, elided lifetimes) within `range`.
+    pub async fn inlay_hints(
+        &self,
+        file_path: impl AsRef<Path>,
+        range: Range,
+    ) -> Result<Vec<InlayHint>> {
+        let uri = Url::from_file_path(file_path.as_ref())
+            .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
+        let hints = self
+            .request::<InlayHintRequest>(InlayHintParams {
+                work_done_progress_params: Default::default(),
+                text_document: TextDocumentIdentifier { uri },
+                range,
+            })
             .await
-            .request::<Rename>(RenameParams {
+            .context("Inlay hint request failed")?;
+        Ok(hints.unwrap_or_default())
+    }
+
+    /// Makes rust-analyzer analyse `text` instead of the file's contents on disk until the
+    /// returned overlay is dropped.
+    pub async fn overlay(&self, file_path: impl AsRef<Path>, text: String) -> Result<Overlay> {
+        let uri = Url::from_file_path(file_path.as_ref())
+            .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
+        self.documents.open(&self.server, uri, text).await
+    }
+
+    /// rust-analyzer's own diagnostics (type errors, unresolved names, ...) for the contents of
+    /// an overlay. The flag is false if they didn't arrive within `timeout`, in which case the
+    /// latest diagnostics of the file are returned.
+    pub async fn overlay_diagnostics(
+        &self,
+        file_path: impl AsRef<Path>,
+        overlay: &Overlay,
+        timeout: Duration,
+    ) -> Result<(Vec<Diagnostic>, bool)> {
+        let uri = Url::from_file_path(file_path.as_ref())
+            .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
+        Ok(self
+            .diagnostics
+            .wait_for(&uri, overlay.version(), timeout)
+            .await)
+    }
+
+    pub async fn completions(
+        &self,
+        file_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Vec<CompletionItem>> {
+        let uri = Url::from_file_path(file_path.as_ref())
+            .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
+        let completions = self
+            .request::<Completion>(CompletionParams {
                 text_document_position: TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier { uri },
                     position,
                 },
-                new_name,
                 work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                context: None,
             })
             .await
-            .context("Rename request failed")
+            .context("Completion request failed")?;
+        Ok(match completions {
+            Some(CompletionResponse::Array(items)) => items,
+            Some(CompletionResponse::List(list)) => list.items,
+            None => Vec::new(),
+        })
+    }
+
+    /// Fills in the documentation and detail of a completion item.
+    pub async fn resolve_completion(&self, item: CompletionItem) -> Result<CompletionItem> {
+        self.request::<ResolveCompletionItem>(item)
+            .await
+            .context("Completion resolve request failed")
+    }
+
+    pub async fn signature_help(
+        &self,
+        file_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<SignatureHelp>> {
+        let uri = Url::from_file_path(file_path.as_ref())
+            .map_err(|_| anyhow::anyhow!("Failed to create file URI from path"))?;
+        self.request::<SignatureHelpRequest>(SignatureHelpParams {
+            context: None,
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .context("Signature help request failed")
+    }
+
+    /// Asks rust-analyzer to reload the workspace, e.g. after a manifest edit.
+    pub async fn reload_workspace(&self) -> Result<()> {
+        // New or removed dependencies change which imports and fixes are available.
+        self.code_action_cache.clear();
+        self.request::<ReloadWorkspace>(())
+            .await
+            .context("Reload workspace request failed")
+    }
+}
+
+/// rust-analyzer extension that re-reads `Cargo.toml` files and reloads the workspace.
+enum ReloadWorkspace {}
+
+impl Request for ReloadWorkspace {
+    type Params = ();
+    type Result = ();
+    const METHOD: &'static str = "rust-analyzer/reloadWorkspace";
+}
+
+/// Sends `$/cancelRequest` for an in-flight request unless disarmed after its response arrived.
+struct CancelOnDrop {
+    server: ServerConnection,
+    id: Option<i32>,
+}
+
+impl CancelOnDrop {
+    fn disarm(mut self) {
+        self.id = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(id) = self.id else {
+            return;
+        };
+        debug!("Cancelling LSP request {}", id);
+        if let Err(e) = self.server.notify::<Cancel>(CancelParams {
+            id: NumberOrString::Number(id),
+        }) {
+            debug!("Failed to send $/cancelRequest for {}: {}", id, e);
+        }
     }
 }
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use async_lsp::{Error, ResponseError};
+use futures::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use futures::{AsyncRead, AsyncWrite, StreamExt, TryStreamExt};
+use lsp_types::NumberOrString;
+use lsp_types::notification::Notification;
+use lsp_types::request::Request;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::oneshot;
+use tracing::{debug, error};
+
+const CONTENT_LENGTH: &str = "Content-Length";
+
+type PendingRequests = HashMap<i32, oneshot::Sender<IncomingMessage>>;
+
+/// Our side of the stdio pipe to rust-analyzer.
+///
+/// Requests and notifications we send go out through here, with request ids we assign ourselves,
+/// so a request can be cancelled by its real id. Responses to them are picked out of
+/// rust-analyzer's output. Everything else is passed on to the async-lsp mainloop, which handles
+/// what rust-analyzer sends us (progress, diagnostics, requests to the client).
+#[derive(Clone, Debug)]
+pub struct ServerConnection {
+    /// Framed messages for the writer task, from us and from the mainloop.
+    frames: flume::Sender<Vec<u8>>,
+    /// Requests waiting for their response. `None` once rust-analyzer's output has ended.
+    pending: Arc<Mutex<Option<PendingRequests>>>,
+    next_id: Arc<AtomicI32>,
+}
+
+impl ServerConnection {
+    /// Starts reading rust-analyzer's `stdout` and writing its `stdin`. Returns the connection and
+    /// the input and output to run the mainloop on.
+    pub fn new(
+        stdout: impl AsyncRead + Send + Unpin + 'static,
+        stdin: impl AsyncWrite + Send + Unpin + 'static,
+    ) -> (Self, impl AsyncRead + Send, impl AsyncWrite + Send) {
+        let (frames, frames_rx) = flume::unbounded::<Vec<u8>>();
+        let (mainloop_tx, mainloop_rx) = flume::unbounded::<Vec<u8>>();
+        let connection = Self {
+            frames: frames.clone(),
+            pending: Arc::new(Mutex::new(Some(HashMap::new()))),
+            next_id: Arc::default(),
+        };
+
+        tokio::spawn(async move {
+            let mut stdin = stdin;
+            while let Ok(frame) = frames_rx.recv_async().await {
+                let written = async {
+                    stdin.write_all(&frame).await?;
+                    stdin.flush().await
+                };
+                if let Err(e) = written.await {
+                    error!("Failed to write to rust-analyzer: {}", e);
+                    break;
+                }
+            }
+        });
+
+        let pending = connection.pending.clone();
+        tokio::spawn(async move {
+            let mut stdout = BufReader::new(stdout);
+            loop {
+                let body = match read_message(&mut stdout).await {
+                    Ok(Some(body)) => body,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Failed to read from rust-analyzer: {}", e);
+                        break;
+                    }
+                };
+                if let Some(body) = take_response(&pending, body)
+                    && mainloop_tx.send(frame(&body)).is_err()
+                {
+                    break;
+                }
+            }
+            // Fails the requests still waiting; dropping `mainloop_tx` ends the mainloop.
+            pending.lock().unwrap().take();
+        });
+
+        let mainloop_input = mainloop_rx
+            .into_stream()
+            .map(Ok::<_, io::Error>)
+            .into_async_read();
+        let mainloop_output = FrameSplitter {
+            buffer: Vec::new(),
+            frames,
+        };
+        (connection, mainloop_input, mainloop_output)
+    }
+
+    /// Sends a request. Its response is awaited through the returned handle.
+    pub fn request<R: Request>(&self, params: R::Params) -> async_lsp::Result<PendingRequest<R>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (response_tx, response) = oneshot::channel();
+        // Registered before sending so a quick response can't miss it.
+        self.pending
+            .lock()
+            .unwrap()
+            .as_mut()
+            .ok_or(Error::ServiceStopped)?
+            .insert(id, response_tx);
+        let sent = serde_json::to_value(params)
+            .map_err(Error::from)
+            .and_then(|params| self.send(message(Some(id), R::METHOD, params)));
+        if let Err(e) = sent {
+            if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+                pending.remove(&id);
+            }
+            return Err(e);
+        }
+        Ok(PendingRequest {
+            id,
+            response,
+            request: PhantomData,
+        })
+    }
+
+    pub fn notify<N: Notification>(&self, params: N::Params) -> async_lsp::Result<()> {
+        let params = serde_json::to_value(params)?;
+        self.send(message(None, N::METHOD, params))
+    }
+
+    fn send(&self, message: Value) -> async_lsp::Result<()> {
+        let body = serde_json::to_vec(&message)?;
+        self.frames
+            .send(frame(&body))
+            .map_err(|_| Error::ServiceStopped)
+    }
+}
+
+/// A request sent through a [`ServerConnection`].
+pub struct PendingRequest<R> {
+    id: i32,
+    response: oneshot::Receiver<IncomingMessage>,
+    request: PhantomData<R>,
+}
+
+impl<R: Request> PendingRequest<R> {
+    /// The id the request was sent with, e.g. for `$/cancelRequest`.
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub async fn response(self) -> async_lsp::Result<R::Result> {
+        let response = self.response.await.map_err(|_| Error::ServiceStopped)?;
+        if let Some(error) = response.error {
+            return Err(error.into());
+        }
+        Ok(serde_json::from_value(response.result.unwrap_or_default())?)
+    }
+}
+
+/// The parts of an incoming message needed to tell whether it answers one of our requests.
+#[derive(Debug, Deserialize)]
+struct IncomingMessage {
+    id: Option<NumberOrString>,
+    method: Option<String>,
+    result: Option<Value>,
+    error: Option<ResponseError>,
+}
+
+/// Delivers `body` to the request waiting for it. Returns it back if it's a message for the
+/// mainloop instead.
+fn take_response(pending: &Mutex<Option<PendingRequests>>, body: Vec<u8>) -> Option<Vec<u8>> {
+    let Ok(message) = serde_json::from_slice::<IncomingMessage>(&body) else {
+        // Let the mainloop report it.
+        return Some(body);
+    };
+    let (None, Some(NumberOrString::Number(id))) = (&message.method, &message.id) else {
+        return Some(body);
+    };
+    let id = *id;
+    let Some(response_tx) = pending
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|pending| pending.remove(&id))
+    else {
+        return Some(body);
+    };
+    if response_tx.send(message).is_err() {
+        debug!("Dropped the response to abandoned LSP request {}", id);
+    }
+    None
+}
+
+fn message(id: Option<i32>, method: &str, params: Value) -> Value {
+    let mut message = json!({ "jsonrpc": "2.0", "method": method });
+    if let Some(id) = id {
+        message["id"] = id.into();
+    }
+    if !params.is_null() {
+        message["params"] = params;
+    }
+    message
+}
+
+fn frame(body: &[u8]) -> Vec<u8> {
+    let mut frame = format!("{}: {}\r\n\r\n", CONTENT_LENGTH, body.len()).into_bytes();
+    frame.extend_from_slice(body);
+    frame
+}
+
+/// Reads the body of the next message, or `None` at the end of the stream.
+async fn read_message(reader: &mut (impl AsyncBufReadExt + Unpin)) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(": ")
+            && name.eq_ignore_ascii_case(CONTENT_LENGTH)
+        {
+            content_length = value.parse::<usize>().ok();
+        }
+    }
+    let content_length = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing content length"))?;
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(body))
+}
+
+/// The mainloop's output. Forwards each complete message to the writer task, so they can't
+/// interleave with ours.
+struct FrameSplitter {
+    buffer: Vec<u8>,
+    frames: flume::Sender<Vec<u8>>,
+}
+
+impl FrameSplitter {
+    /// Length of the complete message at the start of the buffer, if there is one.
+    fn complete_frame_len(&self) -> io::Result<Option<usize>> {
+        let Some(header_len) = self
+            .buffer
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+        else {
+            return Ok(None);
+        };
+        let content_length = String::from_utf8_lossy(&self.buffer[..header_len])
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(": ")?;
+                name.eq_ignore_ascii_case(CONTENT_LENGTH)
+                    .then(|| value.parse::<usize>().ok())?
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing content length"))?;
+        let frame_len = header_len + 4 + content_length;
+        Ok((self.buffer.len() >= frame_len).then_some(frame_len))
+    }
+}
+
+impl AsyncWrite for FrameSplitter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.buffer.extend_from_slice(buf);
+        while let Some(frame_len) = this.complete_frame_len()? {
+            let frame = this.buffer.drain(..frame_len).collect();
+            if this.frames.send(frame).is_err() {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
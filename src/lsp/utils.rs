@@ -1,4 +1,6 @@
-use lsp_types::MarkedString;
+use std::collections::BTreeMap;
+
+use lsp_types::{Documentation, InlayHint, InlayHintLabel, MarkedString};
 
 pub fn format_marked_string(marked_string: &MarkedString) -> String {
     match marked_string {
@@ -9,3 +11,74 @@ pub fn format_marked_string(marked_string: &MarkedString) -> String {
         ),
     }
 }
+
+/// Text of an inlay hint including its padding, e.g. `: Vec<u8>` or `count: `.
+pub fn inlay_hint_label(hint: &InlayHint) -> String {
+    let label = match &hint.label {
+        InlayHintLabel::String(label) => label.clone(),
+        InlayHintLabel::LabelParts(parts) => parts.iter().map(|part| part.value.as_str()).collect(),
+    };
+    let pad = |padding: Option<bool>| if padding == Some(true) { " " } else { "" };
+    format!(
+        "{}{}{}",
+        pad(hint.padding_left),
+        label,
+        pad(hint.padding_right)
+    )
+}
+
+/// Byte offset in `line` of an LSP character offset, which counts UTF-16 code units.
+pub fn utf16_to_byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= character {
+            return offset;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line.len()
+}
+
+/// Renders `lines`, which start at the 0-based `first_line`, with the hints spliced into the
+/// source. Hints at the end of a line (chaining hints mostly) become trailing comments so the
+/// code stays readable. Lines are prefixed with their 1-based numbers.
+pub fn render_inlay_hints(lines: &[&str], first_line: u32, hints: &[InlayHint]) -> String {
+    let mut by_line: BTreeMap<u32, Vec<&InlayHint>> = BTreeMap::new();
+    for hint in hints {
+        by_line.entry(hint.position.line).or_default().push(hint);
+    }
+    let width = (first_line as usize + lines.len()).to_string().len();
+
+    let mut out = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line_number = first_line + i as u32;
+        let mut rendered = String::new();
+        let mut trailing = Vec::new();
+        let mut copied = 0;
+        let mut line_hints = by_line.remove(&line_number).unwrap_or_default();
+        line_hints.sort_by_key(|hint| hint.position.character);
+        for hint in line_hints {
+            let offset = utf16_to_byte_offset(line, hint.position.character).max(copied);
+            if line[offset..].trim().is_empty() {
+                trailing.push(inlay_hint_label(hint).trim().to_string());
+                continue;
+            }
+            rendered.push_str(&line[copied..offset]);
+            rendered.push_str(&inlay_hint_label(hint));
+            copied = offset;
+        }
+        rendered.push_str(&line[copied..]);
+        if !trailing.is_empty() {
+            rendered = format!("{}  // {}", rendered.trim_end(), trailing.join(", "));
+        }
+        out.push_str(&format!("{:>width$} | {}\n", line_number + 1, rendered));
+    }
+    out
+}
+
+pub fn documentation_text(documentation: &Documentation) -> String {
+    match documentation {
+        Documentation::String(text) => text.clone(),
+        Documentation::MarkupContent(content) => content.value.clone(),
+    }
+}
@@ -1,7 +1,11 @@
+mod bench;
+mod cargo_metadata;
 mod cargo_remote;
 mod config_watcher;
 mod context;
+mod crate_docs;
 mod lsp;
+mod manifest_edit;
 mod mcp;
 mod project;
 
@@ -388,6 +392,7 @@ async fn handle_projects(command: ProjectCommands, config_path: PathBuf) -> Resu
             let ser_project = SerProject {
                 root: project.root().clone(),
                 ignore_crates: project.ignore_crates().to_vec(),
+                settings: project.settings().clone(),
             };
 
             config.projects.insert(absolute_path.clone(), ser_project);
@@ -0,0 +1,460 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, TableLike, Value};
+
+use crate::cargo_metadata::{DependencyKind, version_key};
+
+/// One dependency table of a manifest, e.g. `[dev-dependencies]` or
+/// `[target.'cfg(unix)'.dependencies]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DependencyTable {
+    pub kind: DependencyKind,
+    /// Platform cfg or target triple for target-specific tables.
+    pub target: Option<String>,
+}
+
+impl DependencyTable {
+    fn key(&self) -> &'static str {
+        match self.kind {
+            DependencyKind::Normal => "dependencies",
+            DependencyKind::Dev => "dev-dependencies",
+            DependencyKind::Build => "build-dependencies",
+        }
+    }
+}
+
+impl fmt::Display for DependencyTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            Some(target) => write!(f, "[target.'{}'.{}]", target, self.key()),
+            None => write!(f, "[{}]", self.key()),
+        }
+    }
+}
+
+/// A dependency to add to a manifest.
+#[derive(Clone, Debug, Default)]
+pub struct NewDependency {
+    pub name: String,
+    pub version: Option<String>,
+    pub path: Option<String>,
+    pub features: Vec<String>,
+    pub optional: bool,
+    pub default_features: Option<bool>,
+    /// Inherit the dependency from `[workspace.dependencies]`.
+    pub workspace: bool,
+}
+
+/// A `Cargo.toml` loaded for format-preserving edits. Keeps the original text so a failed edit
+/// can be rolled back.
+#[derive(Debug)]
+pub struct ManifestEditor {
+    path: PathBuf,
+    original: String,
+    doc: DocumentMut,
+}
+
+impl ManifestEditor {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let original = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let doc = original
+            .parse::<DocumentMut>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Self {
+            path,
+            original,
+            doc,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(&self.path, self.doc.to_string())
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Writes back the content the manifest had when it was opened.
+    pub fn restore(&self) -> Result<()> {
+        std::fs::write(&self.path, &self.original)
+            .with_context(|| format!("Failed to restore {}", self.path.display()))
+    }
+
+    pub fn has_workspace_dependency(&self, name: &str) -> bool {
+        self.doc
+            .get("workspace")
+            .and_then(|workspace| workspace.get("dependencies"))
+            .and_then(|dependencies| dependencies.get(name))
+            .is_some()
+    }
+
+    /// A field like `version` or `path` of `name` in `[workspace.dependencies]`. An entry that is
+    /// just a string is its version.
+    pub fn workspace_dependency_field(&self, name: &str, field: &str) -> Option<String> {
+        let dependency = self.doc.get("workspace")?.get("dependencies")?.get(name)?;
+        let value = match dependency.as_str() {
+            Some(version) if field == "version" => version,
+            Some(_) => return None,
+            None => dependency.get(field)?.as_str()?,
+        };
+        Some(value.to_string())
+    }
+
+    /// Adds `name = "<version>"` (or a path dependency) to `[workspace.dependencies]`.
+    pub fn add_workspace_dependency(
+        &mut self,
+        name: &str,
+        version: Option<&str>,
+        path: Option<&str>,
+    ) -> Result<()> {
+        let Some(workspace) = self
+            .doc
+            .get_mut("workspace")
+            .and_then(Item::as_table_like_mut)
+        else {
+            bail!("{} has no [workspace] table", self.path.display());
+        };
+        let dependencies = workspace
+            .entry("dependencies")
+            .or_insert(Item::Table(Table::new()))
+            .as_table_like_mut()
+            .context("[workspace.dependencies] is not a table")?;
+        let source = NewDependency {
+            name: name.to_string(),
+            version: version.map(str::to_string),
+            path: path.map(str::to_string),
+            ..Default::default()
+        };
+        insert_sorted(dependencies, name, dependency_item(&source));
+        Ok(())
+    }
+
+    /// The tables of this manifest that declare `name`.
+    pub fn find_dependency(&self, name: &str) -> Vec<DependencyTable> {
+        let mut found = Vec::new();
+        let mut scan = |table: &dyn TableLike, target: Option<&str>| {
+            for kind in DependencyKind::ALL {
+                let location = DependencyTable {
+                    kind,
+                    target: target.map(str::to_string),
+                };
+                if table
+                    .get(location.key())
+                    .and_then(|dependencies| dependencies.get(name))
+                    .is_some()
+                {
+                    found.push(location);
+                }
+            }
+        };
+        scan(self.doc.as_table(), None);
+        if let Some(targets) = self.doc.get("target").and_then(Item::as_table_like) {
+            for (target, table) in targets.iter() {
+                if let Some(table) = table.as_table_like() {
+                    scan(table, Some(target));
+                }
+            }
+        }
+        found
+    }
+
+    pub fn add_dependency(
+        &mut self,
+        table: &DependencyTable,
+        dependency: &NewDependency,
+    ) -> Result<()> {
+        if dependency.workspace && dependency.default_features.is_some() {
+            bail!(
+                "`default-features` can't be set on a workspace-inherited dependency. Set it in [workspace.dependencies] instead."
+            );
+        }
+        let path = self.path.clone();
+        let dependencies = self
+            .table_mut(table, true)
+            .context("Dependency table is not a table")?;
+        if dependencies.contains_key(&dependency.name) {
+            bail!(
+                "`{}` is already in {} of {}. Use set_dependency_features to change it.",
+                dependency.name,
+                table,
+                path.display()
+            );
+        }
+        insert_sorted(dependencies, &dependency.name, dependency_item(dependency));
+        Ok(())
+    }
+
+    pub fn remove_dependency(&mut self, table: &DependencyTable, name: &str) -> Result<()> {
+        let removed = self
+            .table_mut(table, false)
+            .and_then(|dependencies| dependencies.remove(name))
+            .is_some();
+        if !removed {
+            bail!("{}", self.missing_dependency_message(table, name));
+        }
+
+        if let Some(target) = &table.target {
+            self.prune_target_table(target, table.key());
+        }
+
+        // Like `cargo remove`, drop feature references to a dependency that no longer exists.
+        if !self.find_dependency(name).is_empty() {
+            return Ok(());
+        }
+        let Some(features) = self
+            .doc
+            .get_mut("features")
+            .and_then(Item::as_table_like_mut)
+        else {
+            return Ok(());
+        };
+        for (_, values) in features.iter_mut() {
+            let Some(values) = values.as_array_mut() else {
+                continue;
+            };
+            let len = values.len();
+            values.retain(|value| value.as_str().is_none_or(|value| !refers_to(value, name)));
+            // Re-space single-line arrays; multi-line ones keep their per-element layout.
+            if values.len() != len && !values.to_string().contains('\n') {
+                values.fmt();
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds and removes features of an existing dependency and optionally toggles
+    /// `default-features`. Returns the dependency's resulting feature list.
+    pub fn set_dependency_features(
+        &mut self,
+        table: &DependencyTable,
+        name: &str,
+        add: &[String],
+        remove: &[String],
+        default_features: Option<bool>,
+    ) -> Result<Vec<String>> {
+        let missing = self.missing_dependency_message(table, name);
+        let Some(item) = self
+            .table_mut(table, false)
+            .and_then(|dependencies| dependencies.get_mut(name))
+        else {
+            bail!("{}", missing);
+        };
+        // `name = "1.0"` has to become `name = { version = "1.0" }` to carry features.
+        if let Some(version) = item.as_str() {
+            let mut inline = InlineTable::new();
+            inline.insert("version", Value::from(version));
+            *item = Item::Value(Value::InlineTable(inline));
+        }
+        let dependency = item
+            .as_table_like_mut()
+            .context("Dependency entry is not a table")?;
+        let inherited = dependency.get("workspace").and_then(Item::as_bool) == Some(true);
+
+        if let Some(default_features) = default_features {
+            if inherited {
+                bail!(
+                    "`default-features` can't be set on a workspace-inherited dependency. Set it in [workspace.dependencies] instead."
+                );
+            }
+            dependency.remove("default_features");
+            dependency.insert("default-features", toml_edit::value(default_features));
+        }
+
+        let features = dependency
+            .entry("features")
+            .or_insert(toml_edit::value(Array::new()))
+            .as_array_mut()
+            .context("`features` is not an array")?;
+        features.retain(|feature| {
+            feature
+                .as_str()
+                .is_none_or(|feature| !remove.iter().any(|r| r == feature))
+        });
+        for feature in add {
+            if !features
+                .iter()
+                .any(|existing| existing.as_str() == Some(feature))
+            {
+                features.push(feature.as_str());
+            }
+        }
+        let result: Vec<String> = features
+            .iter()
+            .filter_map(|feature| feature.as_str().map(str::to_string))
+            .collect();
+        if result.is_empty() {
+            dependency.remove("features");
+        } else {
+            features.fmt();
+        }
+        Ok(result)
+    }
+
+    /// Drops `[target.<target>.<key>]` and its parents when a removal left them empty.
+    fn prune_target_table(&mut self, target: &str, key: &str) {
+        let Some(targets) = self.doc.get_mut("target").and_then(Item::as_table_like_mut) else {
+            return;
+        };
+        if let Some(platform) = targets.get_mut(target).and_then(Item::as_table_like_mut) {
+            if platform
+                .get(key)
+                .and_then(Item::as_table_like)
+                .is_some_and(|t| t.is_empty())
+            {
+                platform.remove(key);
+            }
+            if platform.is_empty() {
+                targets.remove(target);
+            }
+        }
+        if targets.is_empty() {
+            self.doc.remove("target");
+        }
+    }
+
+    fn missing_dependency_message(&self, table: &DependencyTable, name: &str) -> String {
+        let found = self.find_dependency(name);
+        if found.is_empty() {
+            format!("`{}` is not a dependency in {}.", name, self.path.display())
+        } else {
+            format!(
+                "`{}` is not in {} of {}. It is declared in: {}.",
+                name,
+                table,
+                self.path.display(),
+                found
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+    }
+
+    /// The dependency table for `table`, creating it (and any `[target.*]` parents) if `create` is set.
+    fn table_mut(&mut self, table: &DependencyTable, create: bool) -> Option<&mut dyn TableLike> {
+        let root = self.doc.as_table_mut();
+        let parent: &mut dyn TableLike = match &table.target {
+            None => root,
+            Some(target) if create => root
+                .entry("target")
+                .or_insert(implicit_table())
+                .as_table_like_mut()?
+                .entry(target)
+                .or_insert(implicit_table())
+                .as_table_like_mut()?,
+            Some(target) => root
+                .get_mut("target")?
+                .as_table_like_mut()?
+                .get_mut(target)?
+                .as_table_like_mut()?,
+        };
+        if create {
+            parent
+                .entry(table.key())
+                .or_insert(Item::Table(Table::new()))
+                .as_table_like_mut()
+        } else {
+            parent.get_mut(table.key())?.as_table_like_mut()
+        }
+    }
+}
+
+/// The newest version of `name` in the local registry cache, i.e. one that resolves offline.
+/// Pre-releases are only picked when nothing else is cached.
+pub fn latest_cached_version(name: &str) -> Option<String> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))?;
+    let prefix = format!("{}-", name);
+    let mut versions = Vec::new();
+    for registry in std::fs::read_dir(cargo_home.join("registry").join("cache"))
+        .ok()?
+        .flatten()
+    {
+        let Ok(entries) = std::fs::read_dir(registry.path()) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(version) = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_prefix(&prefix))
+                .and_then(|rest| rest.strip_suffix(".crate"))
+            else {
+                continue;
+            };
+            // `foo-bar-1.0.0.crate` also starts with `foo-`; versions always start with a digit.
+            if version.starts_with(|c: char| c.is_ascii_digit()) {
+                let version = version.split('+').next().unwrap_or(version);
+                versions.push(version.to_string());
+            }
+        }
+    }
+    versions.sort_by_key(|version| (!version.contains('-'), version_key(version)));
+    versions.pop()
+}
+
+fn implicit_table() -> Item {
+    let mut table = Table::new();
+    table.set_implicit(true);
+    Item::Table(table)
+}
+
+/// `name = "1.0"` when only a version is given, otherwise an inline table.
+fn dependency_item(dependency: &NewDependency) -> Item {
+    let simple = !dependency.workspace
+        && dependency.path.is_none()
+        && dependency.features.is_empty()
+        && !dependency.optional
+        && dependency.default_features.is_none();
+    if let (true, Some(version)) = (simple, &dependency.version) {
+        return toml_edit::value(version.as_str());
+    }
+
+    let mut inline = InlineTable::new();
+    if dependency.workspace {
+        inline.insert("workspace", Value::from(true));
+    } else {
+        if let Some(version) = &dependency.version {
+            inline.insert("version", Value::from(version.as_str()));
+        }
+        if let Some(path) = &dependency.path {
+            inline.insert("path", Value::from(path.as_str()));
+        }
+        if let Some(default_features) = dependency.default_features {
+            inline.insert("default-features", Value::from(default_features));
+        }
+    }
+    if !dependency.features.is_empty() {
+        inline.insert(
+            "features",
+            Value::Array(dependency.features.iter().collect()),
+        );
+    }
+    if dependency.optional {
+        inline.insert("optional", Value::from(true));
+    }
+    inline.fmt();
+    Item::Value(Value::InlineTable(inline))
+}
+
+/// Inserts `key`, keeping the table sorted if it already was (as `cargo add` does).
+fn insert_sorted(table: &mut dyn TableLike, key: &str, item: Item) {
+    let keys: Vec<&str> = table.iter().map(|(key, _)| key).collect();
+    let was_sorted = keys.windows(2).all(|pair| pair[0] <= pair[1]);
+    table.insert(key, item);
+    if was_sorted {
+        table.sort_values();
+    }
+}
+
+/// Whether a `[features]` entry refers to dependency `name`.
+fn refers_to(feature: &str, name: &str) -> bool {
+    feature == name
+        || feature == format!("dep:{}", name)
+        || feature.starts_with(&format!("{}/", name))
+        || feature.starts_with(&format!("{}?/", name))
+}
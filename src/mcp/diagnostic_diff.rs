@@ -0,0 +1,163 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::cargo_remote::CompilerMessage;
+
+/// How the diagnostics of a check differ from those of the previous one.
+pub struct DiagnosticDiff<'a> {
+    pub new: Vec<&'a CompilerMessage>,
+    pub resolved: Vec<&'a CompilerMessage>,
+    pub unchanged: usize,
+}
+
+impl<'a> DiagnosticDiff<'a> {
+    /// Pairs up diagnostics with the same identity. When several share one, as many are matched
+    /// as both sides have and the rest count as new or resolved.
+    pub fn new(previous: &'a [CompilerMessage], current: &'a [CompilerMessage]) -> Self {
+        let mut unmatched: HashMap<DiagnosticKey, VecDeque<usize>> = HashMap::new();
+        for (index, diagnostic) in previous.iter().enumerate() {
+            unmatched
+                .entry(DiagnosticKey::new(diagnostic))
+                .or_default()
+                .push_back(index);
+        }
+
+        let mut new = Vec::new();
+        let mut unchanged = 0;
+        for diagnostic in current {
+            let matched = unmatched
+                .get_mut(&DiagnosticKey::new(diagnostic))
+                .and_then(|candidates| candidates.pop_front());
+            match matched {
+                Some(_) => unchanged += 1,
+                None => new.push(diagnostic),
+            }
+        }
+
+        // Keep resolved diagnostics in the order the previous check reported them.
+        let mut resolved: Vec<usize> = unmatched.into_values().flatten().collect();
+        resolved.sort_unstable();
+        let resolved = resolved.into_iter().map(|index| &previous[index]).collect();
+
+        Self {
+            new,
+            resolved,
+            unchanged,
+        }
+    }
+}
+
+/// Identity of a diagnostic across checks: its code, message and the file and source text of its
+/// primary span. Line numbers are left out, so edits elsewhere in the file that shift the
+/// diagnostic up or down don't make it look new.
+#[derive(PartialEq, Eq, Hash)]
+struct DiagnosticKey {
+    code: Option<String>,
+    level: String,
+    message: String,
+    file: Option<String>,
+    source: Vec<String>,
+}
+
+impl DiagnosticKey {
+    fn new(diagnostic: &CompilerMessage) -> Self {
+        let primary = diagnostic.spans.iter().find(|span| span.is_primary);
+        Self {
+            code: diagnostic.code_name().map(str::to_string),
+            level: diagnostic.level.clone(),
+            message: diagnostic.message.clone(),
+            file: primary.map(|span| span.file_name.clone()),
+            source: primary
+                .map(|span| {
+                    span.text
+                        .iter()
+                        .map(|line| line.text.trim().to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An unused-variable warning as rustc reports it, at `line` of `src/lib.rs`.
+    fn unused_variable(name: &str, line: usize) -> CompilerMessage {
+        serde_json::from_value(serde_json::json!({
+            "message": format!("unused variable: `{}`", name),
+            "rendered": format!("warning: unused variable: `{}`\n", name),
+            "code": { "code": "unused_variables", "explanation": null },
+            "level": "warning",
+            "spans": [{
+                "column_start": 9,
+                "column_end": 9 + name.len(),
+                "file_name": "src/lib.rs",
+                "line_start": line,
+                "line_end": line,
+                "is_primary": true,
+                "text": [{
+                    "text": format!("    let {} = compute();", name),
+                    "highlight_start": 9,
+                    "highlight_end": 9 + name.len(),
+                }],
+                "label": null,
+                "suggested_replacement": null,
+                "suggestion_applicability": null,
+                "expansion": null,
+            }],
+            "children": [],
+        }))
+        .unwrap()
+    }
+
+    fn messages(diagnostics: &[&CompilerMessage]) -> Vec<String> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn shifted_diagnostic_is_unchanged() {
+        let previous = [unused_variable("total", 10)];
+        let current = [unused_variable("total", 14)];
+        let diff = DiagnosticDiff::new(&previous, &current);
+        assert!(diff.new.is_empty());
+        assert!(diff.resolved.is_empty());
+        assert_eq!(diff.unchanged, 1);
+    }
+
+    #[test]
+    fn duplicates_are_matched_one_to_one() {
+        let previous = [unused_variable("total", 10)];
+        let current = [unused_variable("total", 10), unused_variable("total", 20)];
+        let diff = DiagnosticDiff::new(&previous, &current);
+        assert_eq!(messages(&diff.new), ["unused variable: `total`"]);
+        assert_eq!(diff.new[0].spans[0].line_start, 20);
+        assert!(diff.resolved.is_empty());
+        assert_eq!(diff.unchanged, 1);
+
+        let diff = DiagnosticDiff::new(&current, &previous);
+        assert!(diff.new.is_empty());
+        assert_eq!(diff.resolved.len(), 1);
+        assert_eq!(diff.unchanged, 1);
+    }
+
+    #[test]
+    fn fixed_diagnostic_is_resolved() {
+        let previous = [
+            unused_variable("total", 10),
+            unused_variable("count", 11),
+            unused_variable("sum", 12),
+        ];
+        let current = [unused_variable("count", 11)];
+        let diff = DiagnosticDiff::new(&previous, &current);
+        assert!(diff.new.is_empty());
+        assert_eq!(
+            messages(&diff.resolved),
+            ["unused variable: `total`", "unused variable: `sum`"]
+        );
+        assert_eq!(diff.unchanged, 1);
+    }
+}
@@ -1,4 +1,6 @@
+mod diagnostic_diff;
 mod server;
+mod symbol_path;
 mod utils;
 
 use std::net::SocketAddr;
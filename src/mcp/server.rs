@@ -1,20 +1,51 @@
-use crate::context::Context as AppContext;
-use crate::lsp::format_marked_string;
+use crate::bench::{BaselineStore, Verdict, compare};
+use crate::cargo_metadata::{DependencyKind, DependencyNode, Metadata, Package, TreeOptions};
+use crate::cargo_remote::{
+    BenchOutcome, CompilerMessage, CompilerMessageSpan, FormatTarget, LintOverrides, RunOptions,
+    RunResult, RunTarget, explanation_excerpt,
+};
+use crate::context::{Context as AppContext, ProjectContext};
+use crate::lsp::{
+    documentation_text, format_marked_string, inlay_hint_label, render_inlay_hints,
+    utf16_to_byte_offset,
+};
+use crate::manifest_edit::{DependencyTable, ManifestEditor, NewDependency, latest_cached_version};
 use crate::mcp::McpNotification;
+use crate::mcp::diagnostic_diff::DiagnosticDiff;
+use crate::project::ProjectSettings;
 use crate::mcp::utils::{
-    error_response, get_file_lines, resolve_symbol_in_project,
+    SymbolQuery, SymbolTarget, TargetArgs, error_response, get_file_lines, open_overlay,
+    open_overlay_with, resolve_cursor, resolve_file_path, resolve_symbol_target, symbol_origin,
+    text_lines,
 };
 
 use dashmap::DashMap;
 use lsp_types::HoverContents;
 use rmcp::{
-    ServerHandler, model::*, schemars, service::RequestContext as RmcpRequestContext,
-    service::RoleServer, tool,
+    ServerHandler, handler::server::tool::ToolCallContext, model::*, schemars,
+    service::RequestContext as RmcpRequestContext, service::RoleServer, tool,
 };
 use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
 const GUIDANCE_PROMPT: &str = include_str!("guidance_prompt.md");
+/// How many of the top completion items get their documentation resolved.
+const RESOLVED_COMPLETIONS: usize = 5;
+/// How long `check_unsaved_file` waits for rust-analyzer to publish diagnostics.
+const UNSAVED_DIAGNOSTICS_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long LSP-backed tools wait for rust-analyzer to finish indexing before giving up.
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+/// How many code-action requests `check_project(include_fixes=true)` keeps in flight at once.
+const MAX_CONCURRENT_FIX_LOOKUPS: usize = 16;
+/// Changes smaller than this many percent are reported as unchanged by `bench_project`.
+const DEFAULT_BENCH_NOISE_THRESHOLD: f64 = 5.0;
+/// How long `run_binary` and `run_example` let the program run unless told otherwise.
+const DEFAULT_RUN_TIMEOUT_SECS: u64 = 60;
 
 // Code actions that can be executed
 #[derive(Debug, Clone, Serialize)]
@@ -80,10 +111,10 @@ impl DevToolsServer {
         
         // 获取项目诊断信息并生成新的code actions
         match project.cargo_remote.check_structured().await {
-            Ok(diagnostics) => {
+            Ok(report) => {
                 let action_count = 0;
                 
-                for diagnostic in diagnostics {
+                for diagnostic in report.diagnostics {
                     // Extract primary span information
                     if let Some(primary_span) = diagnostic.spans.iter().find(|span| span.is_primary) {
                         // Store diagnostic information
@@ -94,14 +125,11 @@ impl DevToolsServer {
                             primary_span.column_start
                         );
                         
-                        let diagnostic_with_fixes = DiagnosticWithFixes {
-                            file_path: primary_span.file_name.clone(),
-                            severity: diagnostic.level.clone(),
-                            message: diagnostic.rendered.clone(),
-                            line: primary_span.line_start,
-                            character: primary_span.column_start,
-                            available_fixes: Vec::new(), // CompilerMessage doesn't have fixes field
-                        };
+                        let diagnostic_with_fixes = DiagnosticWithFixes::new(
+                            &diagnostic,
+                            primary_span,
+                            Vec::new(), // CompilerMessage doesn't have fixes field
+                        );
                         
                         self.diagnostics.insert(diagnostic_id, diagnostic_with_fixes);
                     }
@@ -147,7 +175,153 @@ impl DevToolsServer {
         // Generate simple, descriptive action IDs that LLMs can understand
         format!("{}_{}", operation, target.replace(" ", "_").replace("::", "_"))
     }
+
+    /// Formats the Rust files touched by an applied edit if the project opted into `format_on_edit`.
+    /// Returns whether any file was reformatted.
+    async fn format_edited_files(&self, project_path: &PathBuf, files: Vec<PathBuf>) -> bool {
+        let Some(project) = self.context.get_project(project_path).await else {
+            return false;
+        };
+        let files: Vec<PathBuf> = files
+            .into_iter()
+            .filter(|file| file.extension().is_some_and(|ext| ext == "rs"))
+            .collect();
+        if !project.project.settings().format_on_edit || files.is_empty() {
+            return false;
+        }
+
+        match project
+            .cargo_remote
+            .format(&FormatTarget::Files(files), true)
+            .await
+        {
+            Ok(outcome) => outcome.applied,
+            Err(e) => {
+                tracing::warn!("Failed to format edited files: {}", e);
+                false
+            }
+        }
+    }
     
+    /// Timeout for a tool call, taken from the settings of the project the call targets.
+    /// Mirrors `get_project_name` without updating `last_project`.
+    async fn tool_timeout(&self, tool: &str, arguments: Option<&JsonObject>) -> Duration {
+        let explicit_name = arguments
+            .and_then(|args| args.get("project_name"))
+            .and_then(|name| name.as_str())
+            .map(str::to_string);
+        let last_name = self.last_project.read().ok().and_then(|name| name.clone());
+        let project_name = match explicit_name.or(last_name) {
+            Some(name) => Some(name),
+            None => self
+                .context
+                .project_descriptions()
+                .await
+                .first()
+                .map(|project| project.name.clone()),
+        };
+
+        let root = match project_name {
+            Some(name) => self.context.find_project_by_name(&name).await,
+            None => None,
+        };
+        let project = match root {
+            Some(root) => self.context.get_project(&root).await,
+            None => None,
+        };
+        match project {
+            Some(project) => project.project.settings().tool_timeout(tool),
+            None => ProjectSettings::default().tool_timeout(tool),
+        }
+    }
+
+    /// Waits for rust-analyzer to finish loading the project before answering a query from it.
+    /// If it doesn't within `READY_TIMEOUT`, returns the error result to hand back instead,
+    /// carrying the indexing progress as structured content.
+    async fn wait_until_ready(&self, project: &ProjectContext) -> Result<(), CallToolResult> {
+        let Err(not_ready) = project.readiness.wait(READY_TIMEOUT).await else {
+            return Ok(());
+        };
+        let mut content = vec![Content::text(not_ready.to_string())];
+        match Content::json(serde_json::json!({ "not_ready": not_ready })) {
+            Ok(json) => content.push(json),
+            Err(e) => tracing::warn!("Failed to serialize readiness state: {:?}", e),
+        }
+        Err(CallToolResult::error(content))
+    }
+
+    /// Saves edited manifests and checks that cargo still resolves the workspace offline.
+    /// If it doesn't, every manifest is restored. Otherwise rust-analyzer reloads the workspace.
+    async fn save_manifests(&self, project: &ProjectContext, editors: &[&ManifestEditor]) -> Result<(), String> {
+        let restore = || {
+            for editor in editors {
+                if let Err(e) = editor.restore() {
+                    tracing::error!("{}", e);
+                }
+            }
+            project.cargo_remote.invalidate_metadata();
+        };
+
+        for editor in editors {
+            if let Err(e) = editor.save() {
+                restore();
+                return Err(e.to_string());
+            }
+        }
+        project.cargo_remote.invalidate_metadata();
+        if let Err(e) = project.cargo_remote.metadata().await {
+            restore();
+            return Err(format!(
+                "The edit was reverted because cargo could not resolve the workspace offline: {}",
+                e
+            ));
+        }
+
+        if let Err(e) = project.lsp.reload_workspace().await {
+            tracing::warn!("Failed to reload rust-analyzer workspace after manifest edit: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Shared body of `run_binary` and `run_example`. A failed build is reported with its errors
+    /// instead of running a stale executable.
+    async fn run_target(
+        &self,
+        project_name: Option<String>,
+        target: RunTarget,
+        options: RunOptions,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let result = match project.cargo_remote.run(&target, &options).await {
+            Ok(RunResult::Finished(outcome)) => {
+                CallToolResult::success(vec![Content::json(outcome)?])
+            }
+            Ok(RunResult::BuildFailed(report)) => {
+                let mut content = vec![Content::text("The build failed, nothing was run.".to_string())];
+                content.extend(
+                    report
+                        .diagnostics
+                        .iter()
+                        .filter(|diagnostic| diagnostic.level == "error")
+                        .map(|diagnostic| Content::text(diagnostic.rendered.clone())),
+                );
+                CallToolResult::error(content)
+            }
+            Err(e) => error_response(&e.to_string()),
+        };
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
     async fn get_project_name(&self, project_name: Option<String>) -> Result<String, rmcp::Error> {
         match project_name {
             Some(name) => {
@@ -220,9 +394,344 @@ struct DiagnosticWithFixes {
     message: String,
     line: usize,
     character: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    /// Every location the diagnostic points at, primary first, with rustc's labels.
+    spans: Vec<DiagnosticSpan>,
+    /// `note:` and `help:` sub-diagnostics, including suggestions.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<DiagnosticChild>,
     available_fixes: Vec<Fix>,
 }
 
+impl DiagnosticWithFixes {
+    fn new(diagnostic: &CompilerMessage, primary: &CompilerMessageSpan, available_fixes: Vec<Fix>) -> Self {
+        let mut spans: Vec<&CompilerMessageSpan> = diagnostic.spans.iter().collect();
+        spans.sort_by_key(|span| !span.is_primary);
+        Self {
+            file_path: primary.file_name.clone(),
+            severity: diagnostic.level.clone(),
+            message: diagnostic.rendered.clone(),
+            line: primary.line_start,
+            character: primary.column_start,
+            code: diagnostic.code_name().map(str::to_string),
+            spans: spans.into_iter().map(DiagnosticSpan::from).collect(),
+            children: diagnostic
+                .children
+                .iter()
+                .map(|child| DiagnosticChild {
+                    level: child.level.clone(),
+                    message: child.message.clone(),
+                    spans: child.spans.iter().map(DiagnosticSpan::from).collect(),
+                })
+                .collect(),
+            available_fixes,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DiagnosticSpan {
+    file_path: String,
+    line: usize,
+    character: usize,
+    end_line: usize,
+    end_character: usize,
+    is_primary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggested_replacement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggestion_applicability: Option<String>,
+    /// Macro invocations the span was expanded from, innermost first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    macro_backtrace: Vec<MacroFrame>,
+}
+
+impl From<&CompilerMessageSpan> for DiagnosticSpan {
+    fn from(span: &CompilerMessageSpan) -> Self {
+        let mut macro_backtrace = Vec::new();
+        let mut expansion = span.expansion.as_deref();
+        while let Some(frame) = expansion {
+            macro_backtrace.push(MacroFrame {
+                macro_name: frame.macro_decl_name.clone(),
+                file_path: frame.span.file_name.clone(),
+                line: frame.span.line_start,
+                character: frame.span.column_start,
+            });
+            expansion = frame.span.expansion.as_deref();
+        }
+        Self {
+            file_path: span.file_name.clone(),
+            line: span.line_start,
+            character: span.column_start,
+            end_line: span.line_end,
+            end_character: span.column_end,
+            is_primary: span.is_primary,
+            label: span.label.clone(),
+            suggested_replacement: span.suggested_replacement.clone(),
+            suggestion_applicability: span.suggestion_applicability.clone(),
+            macro_backtrace,
+        }
+    }
+}
+
+/// Where a macro was invoked.
+#[derive(Serialize)]
+struct MacroFrame {
+    macro_name: String,
+    file_path: String,
+    line: usize,
+    character: usize,
+}
+
+#[derive(Serialize)]
+struct DiagnosticChild {
+    level: String,
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    spans: Vec<DiagnosticSpan>,
+}
+
+#[derive(Serialize)]
+struct LintOccurrence {
+    file_path: String,
+    line: usize,
+    character: usize,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct LintGroup {
+    lint: String,
+    level: String,
+    count: usize,
+    occurrences: Vec<LintOccurrence>,
+}
+
+/// LSP range of a compiler span, whose lines and columns are 1-based.
+fn span_range(span: &CompilerMessageSpan) -> lsp_types::Range {
+    lsp_types::Range {
+        start: lsp_types::Position {
+            line: span.line_start.saturating_sub(1) as u32,
+            character: span.column_start.saturating_sub(1) as u32,
+        },
+        end: lsp_types::Position {
+            line: span.line_end.saturating_sub(1) as u32,
+            character: span.column_end.saturating_sub(1) as u32,
+        },
+    }
+}
+
+/// Structured form of diagnostics that have a primary span. With `lookup_fixes`, rust-analyzer
+/// is asked for the code actions of each distinct span, several at a time.
+async fn diagnostics_with_fixes(
+    project: &Arc<ProjectContext>,
+    diagnostics: &[&CompilerMessage],
+    lookup_fixes: bool,
+) -> Vec<DiagnosticWithFixes> {
+    let mut lookups = JoinSet::new();
+    if lookup_fixes {
+        let limit = Arc::new(Semaphore::new(MAX_CONCURRENT_FIX_LOOKUPS));
+        let spans: HashSet<(PathBuf, lsp_types::Range)> = diagnostics
+            .iter()
+            .filter_map(|diag| diag.spans.iter().find(|s| s.is_primary))
+            .map(|span| (project.project.root().join(&span.file_name), span_range(span)))
+            .collect();
+        for (absolute_path, range) in spans {
+            let project = project.clone();
+            let limit = limit.clone();
+            lookups.spawn(async move {
+                let _permit = limit.acquire_owned().await;
+                let actions = project.lsp.cached_code_actions(&absolute_path, range).await;
+                ((absolute_path, range), actions)
+            });
+        }
+    }
+    let mut fixes_by_span = HashMap::new();
+    while let Some(lookup) = lookups.join_next().await {
+        match lookup {
+            Ok((span, Ok(Some(actions)))) => {
+                fixes_by_span.insert(span, actions);
+            }
+            Ok((_, Ok(None))) => {}
+            Ok(((path, _), Err(e))) => {
+                tracing::debug!("Fix lookup for {} failed: {}", path.display(), e);
+            }
+            Err(e) => tracing::warn!("Fix lookup task failed: {}", e),
+        }
+    }
+
+    let mut results = Vec::new();
+    for diag in diagnostics {
+        if let Some(span) = diag.spans.iter().find(|s| s.is_primary) {
+            let absolute_path = project.project.root().join(&span.file_name);
+            let available_fixes = fixes_by_span
+                .get(&(absolute_path, span_range(span)))
+                .into_iter()
+                .flatten()
+                .filter_map(|action_or_cmd| {
+                    if let lsp_types::CodeActionOrCommand::CodeAction(action) = action_or_cmd {
+                        Some(Fix {
+                            title: action.title.clone(),
+                            kind: action.kind.clone(),
+                            edit_to_apply: action.edit.clone(),
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            results.push(DiagnosticWithFixes::new(diag, span, available_fixes));
+        }
+    }
+    results
+}
+
+/// Opening paragraph of rustc's explanation for each distinct error code among `diagnostics`.
+/// rustc includes the explanation in its JSON output; `rustc --explain` is only run for codes
+/// that came without one.
+async fn error_code_excerpts(
+    project: &ProjectContext,
+    diagnostics: &[CompilerMessage],
+) -> BTreeMap<String, String> {
+    let mut excerpts = BTreeMap::new();
+    let mut unexplained = BTreeSet::new();
+    let codes = diagnostics
+        .iter()
+        .filter_map(|diagnostic| diagnostic.code.as_ref())
+        .filter(|code| code.is_error_code());
+    for code in codes {
+        match &code.explanation {
+            Some(explanation) => {
+                excerpts
+                    .entry(code.code.clone())
+                    .or_insert_with(|| explanation_excerpt(explanation));
+            }
+            None => {
+                unexplained.insert(code.code.as_str());
+            }
+        }
+    }
+    for code in unexplained {
+        if excerpts.contains_key(code) {
+            continue;
+        }
+        match project.cargo_remote.explain(code).await {
+            Ok(explanation) => {
+                excerpts.insert(code.to_string(), explanation_excerpt(&explanation));
+            }
+            Err(e) => tracing::debug!("Failed to explain {}: {}", code, e),
+        }
+    }
+    excerpts
+}
+
+/// Path relative to the workspace root when it lies inside it.
+fn workspace_relative(metadata: &Metadata, path: &std::path::Path) -> String {
+    path.strip_prefix(&metadata.workspace_root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// One-line-per-field overview of a package, used in workspace listings.
+fn package_summary(metadata: &Metadata, package: &Package) -> serde_json::Value {
+    serde_json::json!({
+        "name": package.name,
+        "version": package.version,
+        "edition": package.edition,
+        "manifest_path": workspace_relative(metadata, &package.manifest_path),
+        "targets": package.targets.iter().map(|target| serde_json::json!({
+            "name": target.name,
+            "kind": target.kind,
+        })).collect::<Vec<_>>(),
+        "features": package.features.keys().collect::<Vec<_>>(),
+        "dependency_count": package.dependencies.len(),
+    })
+}
+
+/// Full description of a package: targets, feature definitions, resolved features and dependencies.
+fn package_details(metadata: &Metadata, package: &Package) -> serde_json::Value {
+    let enabled_features = metadata
+        .resolved_node(&package.id)
+        .map(|node| node.features.clone())
+        .unwrap_or_default();
+    serde_json::json!({
+        "name": package.name,
+        "version": package.version,
+        "edition": package.edition,
+        "is_workspace_member": metadata.workspace_members.contains(&package.id),
+        "manifest_path": workspace_relative(metadata, &package.manifest_path),
+        "targets": package.targets.iter().map(|target| serde_json::json!({
+            "name": target.name,
+            "kind": target.kind,
+            "src_path": workspace_relative(metadata, &target.src_path),
+            "required_features": target.required_features,
+        })).collect::<Vec<_>>(),
+        "features": package.features,
+        "enabled_features": enabled_features,
+        "dependencies": package.dependencies.iter().map(|dependency| serde_json::json!({
+            "name": dependency.rename.as_ref().unwrap_or(&dependency.name),
+            "package": dependency.name,
+            "req": dependency.req,
+            "kind": dependency.kind.as_deref().unwrap_or("normal"),
+            "optional": dependency.optional,
+            "default_features": dependency.uses_default_features,
+            "features": dependency.features,
+            "target": dependency.target,
+            "path": dependency.path.as_ref().map(|path| workspace_relative(metadata, path)),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Manifest of the workspace member to edit: the named package, else the only member or the
+/// package at the workspace root.
+fn member_manifest(metadata: &Metadata, package: Option<&str>) -> Result<PathBuf, String> {
+    let members: Vec<&Package> = metadata.workspace_packages().collect();
+    let member = match package {
+        Some(name) => members.iter().find(|member| member.name == name),
+        None if members.len() == 1 => members.first(),
+        None => members
+            .iter()
+            .find(|member| member.root() == metadata.workspace_root),
+    };
+    match member {
+        Some(member) => Ok(member.manifest_path.clone()),
+        None => Err(format!(
+            "{} Workspace members: {}.",
+            match package {
+                Some(name) => format!("'{}' is not a workspace member.", name),
+                None => "Specify 'package': the workspace has several members.".to_string(),
+            },
+            members.iter().map(|member| member.name.as_str()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+fn dependency_table(kind: Option<String>, target: Option<String>) -> Result<DependencyTable, String> {
+    let kind = match kind.as_deref() {
+        None => DependencyKind::Normal,
+        Some(kind) => DependencyKind::parse(kind).ok_or_else(|| {
+            format!("Unknown dependency kind '{}'. Use 'normal', 'dev' or 'build'.", kind)
+        })?,
+    };
+    Ok(DependencyTable { kind, target })
+}
+
+fn workspace_summary(metadata: &Metadata) -> serde_json::Value {
+    serde_json::json!({
+        "workspace_root": metadata.workspace_root,
+        "target_directory": metadata.target_directory,
+        "members": metadata
+            .workspace_packages()
+            .map(|package| package_summary(metadata, package))
+            .collect::<Vec<_>>(),
+    })
+}
+
 
 
 #[tool(tool_box)]
@@ -365,20 +874,43 @@ impl DevToolsServer {
 
     #[tool(
         name = "get_symbol_info",
-        description = "Get comprehensive information (documentation, definition, location) for a symbol within a project."
+        description = "Get comprehensive information (documentation, definition, location) for a symbol within a project. With scope 'dependencies' it also finds items from dependencies and std. Instead of a name, a file position can be given to inspect locals, closure parameters or any other expression."
     )]
+    #[allow(clippy::too_many_arguments)]
     async fn get_symbol_info(
         &self,
         #[tool(param)]
         #[schemars(description = "The name of the project to search in. If not provided, uses the most recently used project.")]
         project_name: Option<String>,
         #[tool(param)]
-        #[schemars(description = "The name of the symbol to get information for.")]
-        symbol_name: String,
+        #[schemars(description = "The symbol to get information for: a bare name or a Rust path such as `crate::context::Context`, `RustAnalyzerLsp::hover` or `<Foo as Bar>::baz`. Omit it when giving file and line.")]
+        symbol_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Instead of symbol_name: the file containing the position to look at (absolute, project-relative or a file name).")]
+        file: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "1-based line of the position, used with file.")]
+        line: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "1-based column of the position, used with file and line.")]
+        column: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "Instead of column: a snippet on the line that starts at the symbol, e.g. 'count' or 'count +='.")]
+        text: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional unsaved contents of the whole file, analysed instead of the file on disk. Used with file and line.")]
+        content: Option<String>,
         #[tool(param)]
-        #[schemars(description = "Optional file path hint to help locate the symbol more efficiently.")]
-        file_hint: Option<String>,
+        #[schemars(description = "Where to search: 'workspace' (default) or 'dependencies' (workspace, dependencies and std).")]
+        scope: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional symbol kinds to accept, e.g. ['function', 'method']. Any of: function, method, macro, struct, union, enum, variant, trait, type_alias, module, constant, static, field.")]
+        kinds: Option<Vec<String>>,
     ) -> Result<CallToolResult, rmcp::Error> {
+        let query = match SymbolQuery::new(scope.as_deref(), kinds.as_deref()) {
+            Ok(query) => query,
+            Err(e) => return Ok(error_response(&e)),
+        };
         let project_name = self.get_project_name(project_name).await?;
         
         let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
@@ -388,22 +920,54 @@ impl DevToolsServer {
             )));
         };
         let project = self.context.get_project(&project_path).await.unwrap();
+        if let Err(not_ready) = self.wait_until_ready(&project).await {
+            return Ok(not_ready);
+        }
 
-        let symbol_info =
-            match resolve_symbol_in_project(&project, &symbol_name, file_hint.as_deref()).await {
-                Ok(info) => info,
-                Err(e) => return Ok(error_response(&e)),
-            };
-
-        let file_path = symbol_info.location.uri.to_file_path().map_err(|_| {
-            rmcp::Error::internal_error("Invalid file path in symbol location", None)
-        })?;
+        let args = TargetArgs {
+            symbol_name,
+            file,
+            line,
+            column,
+            text,
+            content: content.clone(),
+        };
+        let target = match resolve_symbol_target(&project, &args, &query).await {
+            Ok(target) => target,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let target_file = match target.file() {
+            Ok(file) => file,
+            Err(e) => return Ok(error_response(&e)),
+        };
 
         let hover = project
             .lsp
-            .hover(&file_path, symbol_info.location.range.start)
+            .hover(&target_file, target.position())
             .await
             .unwrap_or(None);
+
+        // A position may be a use site, so follow it to the definition.
+        let (file_path, range, kind, container) = match &target {
+            SymbolTarget::Symbol(symbol) => (
+                target_file,
+                symbol.location.range,
+                Some(format!("{:?}", symbol.kind)),
+                symbol.container_name.clone(),
+            ),
+            SymbolTarget::Position { position, .. } => {
+                let definition = project
+                    .lsp
+                    .definition(&target_file, *position)
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find_map(|location| Some((location.uri.to_file_path().ok()?, location.range)));
+                let (file_path, range) = definition
+                    .unwrap_or((target_file, lsp_types::Range::new(*position, *position)));
+                (file_path, range, None, None)
+            }
+        };
         let documentation = hover.map_or_else(
             || "No documentation found.".to_string(),
             |h| match h.contents {
@@ -417,23 +981,26 @@ impl DevToolsServer {
             },
         );
 
-        let definition_code = get_file_lines(
-            &file_path,
-            symbol_info.location.range.start.line,
-            symbol_info.location.range.end.line,
-            2,
-            5,
-        )
-        .unwrap_or(None)
+        // A definition in the unsaved contents may not exist on disk yet.
+        let unsaved = content.filter(|_| {
+            matches!(&target, SymbolTarget::Position { file, .. } if *file == file_path)
+        });
+        let definition_code = match unsaved {
+            Some(content) => text_lines(&content, range.start.line, range.end.line, 2, 5),
+            None => get_file_lines(&file_path, range.start.line, range.end.line, 2, 5)
+                .unwrap_or(None),
+        }
         .unwrap_or_else(|| "Could not read source file.".to_string());
 
         let result_json = serde_json::json!({
-            "symbol": symbol_info.name,
-            "kind": format!("{:?}", symbol_info.kind),
+            "symbol": target.name(),
+            "kind": kind,
             "file_path": file_path.display().to_string(),
+            "origin": symbol_origin(&file_path, &project_path),
+            "container": container,
             "position": {
-                "start_line": symbol_info.location.range.start.line,
-                "end_line": symbol_info.location.range.end.line,
+                "start_line": range.start.line,
+                "end_line": range.end.line,
             },
             "documentation": documentation,
             "definition_code": definition_code,
@@ -452,20 +1019,43 @@ impl DevToolsServer {
 
     #[tool(
         name = "find_symbol_usages",
-        description = "Find all usages of a symbol across the entire project."
+        description = "Find all usages of a symbol across the entire project. Instead of a name, a file position can be given, which also works for locals and closure parameters."
     )]
+    #[allow(clippy::too_many_arguments)]
     async fn find_symbol_usages(
         &self,
         #[tool(param)]
         #[schemars(description = "The name of the project to search in. If not provided, uses the most recently used project.")]
         project_name: Option<String>,
         #[tool(param)]
-        #[schemars(description = "The name of the symbol to find usages for.")]
-        symbol_name: String,
+        #[schemars(description = "The symbol to find usages for: a bare name or a Rust path such as `crate::context::Context`, `RustAnalyzerLsp::hover` or `<Foo as Bar>::baz`. Omit it when giving file and line.")]
+        symbol_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Instead of symbol_name: the file containing the position to look at (absolute, project-relative or a file name).")]
+        file: Option<String>,
         #[tool(param)]
-        #[schemars(description = "Optional file path hint to help locate the symbol more efficiently.")]
-        file_hint: Option<String>,
+        #[schemars(description = "1-based line of the position, used with file.")]
+        line: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "1-based column of the position, used with file and line.")]
+        column: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "Instead of column: a snippet on the line that starts at the symbol, e.g. 'count' or 'count +='.")]
+        text: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional unsaved contents of the whole file, analysed instead of the file on disk. Used with file and line.")]
+        content: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Where to search: 'workspace' (default) or 'dependencies' (workspace, dependencies and std).")]
+        scope: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional symbol kinds to accept, e.g. ['function', 'method']. Any of: function, method, macro, struct, union, enum, variant, trait, type_alias, module, constant, static, field.")]
+        kinds: Option<Vec<String>>,
     ) -> Result<CallToolResult, rmcp::Error> {
+        let query = match SymbolQuery::new(scope.as_deref(), kinds.as_deref()) {
+            Ok(query) => query,
+            Err(e) => return Ok(error_response(&e)),
+        };
         let project_name = self.get_project_name(project_name).await?;
         
         let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
@@ -475,20 +1065,30 @@ impl DevToolsServer {
             )));
         };
         let project = self.context.get_project(&project_path).await.unwrap();
+        if let Err(not_ready) = self.wait_until_ready(&project).await {
+            return Ok(not_ready);
+        }
 
-        let symbol_info =
-            match resolve_symbol_in_project(&project, &symbol_name, file_hint.as_deref()).await {
-                Ok(info) => info,
-                Err(e) => return Ok(error_response(&e)),
-            };
-
-        let symbol_file_path = symbol_info.location.uri.to_file_path().map_err(|_| {
-            rmcp::Error::internal_error("Invalid file path in symbol location", None)
-        })?;
+        let args = TargetArgs {
+            symbol_name,
+            file,
+            line,
+            column,
+            text,
+            content,
+        };
+        let target = match resolve_symbol_target(&project, &args, &query).await {
+            Ok(target) => target,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let symbol_file_path = match target.file() {
+            Ok(file) => file,
+            Err(e) => return Ok(error_response(&e)),
+        };
 
         let references = project
             .lsp
-            .find_references(&symbol_file_path, symbol_info.location.range.start)
+            .find_references(&symbol_file_path, target.position())
             .await
             .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?
             .ok_or_else(|| rmcp::Error::internal_error("No references found", None))?;
@@ -527,22 +1127,44 @@ impl DevToolsServer {
         Ok(result)
     }
 
-    // --- Project Health ---
     #[tool(
-        name = "check_project",
-        description = "Checks the project for errors/warnings. Returns human-readable messages by default, or structured diagnostics with fixes when include_fixes=true."
+        name = "show_inferred_types",
+        description = "Shows a file range with rust-analyzer's inlay hints: inferred types of bindings, closure return types, types along iterator chains, parameter names and elided lifetimes. Returns the source with hints rendered inline, or a list of hints."
     )]
-    async fn check_project(
+    #[allow(clippy::too_many_arguments)]
+    async fn show_inferred_types(
         &self,
         #[tool(param)]
-        #[schemars(description = "The name of the project to check for errors and warnings. If not provided, uses the most recently used project.")]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
         project_name: Option<String>,
         #[tool(param)]
-        #[schemars(description = "Whether to include structured diagnostics with available fixes. Default is false for human-readable output.")]
-        include_fixes: Option<bool>,
-    ) -> Result<CallToolResult, rmcp::Error> {        
+        #[schemars(description = "The file to annotate (absolute, project-relative or a file name).")]
+        file: String,
+        #[tool(param)]
+        #[schemars(description = "First line to show (1-based, inclusive). Defaults to the start of the file.")]
+        start_line: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "Last line to show (1-based, inclusive). Defaults to the end of the file.")]
+        end_line: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "'inline' (default) for numbered source with hints spliced in, or 'list' for {line, column, kind, label} entries.")]
+        format: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional unsaved contents of the whole file, analysed instead of the file on disk.")]
+        content: Option<String>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let as_list = match format.as_deref() {
+            None | Some("inline") => false,
+            Some("list") => true,
+            Some(other) => {
+                return Ok(error_response(&format!(
+                    "Unknown format '{}'. Use 'inline' or 'list'.",
+                    other
+                )));
+            }
+        };
         let project_name = self.get_project_name(project_name).await?;
-        
+
         let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
             return Ok(error_response(&format!(
                 "Project '{}' not found.",
@@ -550,69 +1172,436 @@ impl DevToolsServer {
             )));
         };
         let project = self.context.get_project(&project_path).await.unwrap();
+        if let Err(not_ready) = self.wait_until_ready(&project).await {
+            return Ok(not_ready);
+        }
 
-        let include_fixes = include_fixes.unwrap_or(false);
-
-        if include_fixes {
-            // Return structured diagnostics with fixes
-            let diagnostics = project
-                .cargo_remote
-                .check_structured()
-                .await
-                .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
-
-
+        let file_path = match resolve_file_path(&project, &file).await {
+            Ok(path) => path,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let _overlay = match open_overlay(&project, &file_path, content.clone()).await {
+            Ok(overlay) => overlay,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let content = match content.map_or_else(|| std::fs::read_to_string(&file_path), Ok) {
+            Ok(content) => content,
+            Err(e) => {
+                return Ok(error_response(&format!(
+                    "Failed to read '{}': {}",
+                    file_path.display(),
+                    e
+                )));
+            }
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        let first = start_line.unwrap_or(1).max(1);
+        let last = end_line.unwrap_or(lines.len() as u32).min(lines.len() as u32);
+        if lines.is_empty() || first > last {
+            return Ok(error_response(&format!(
+                "Invalid line range {}-{} for '{}' ({} lines).",
+                first,
+                last,
+                file_path.display(),
+                lines.len()
+            )));
+        }
+        let lines = &lines[first as usize - 1..last as usize];
+
+        let range = lsp_types::Range::new(
+            lsp_types::Position::new(first - 1, 0),
+            lsp_types::Position::new(
+                last - 1,
+                lines.last().map_or(0, |line| line.encode_utf16().count() as u32),
+            ),
+        );
+        let hints = project
+            .lsp
+            .inlay_hints(&file_path, range)
+            .await
+            .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
 
-            // Process diagnostics sequentially for now (async LSP calls don't benefit from rayon)
-            // Future optimization: batch LSP requests for better performance
-            let mut results = Vec::new();
-            for diag in diagnostics {
-                if let Some(span) = diag.spans.iter().find(|s| s.is_primary) {
-                    let absolute_path = project.project.root().join(&span.file_name);
-                    let range = lsp_types::Range {
-                        start: lsp_types::Position {
-                            line: span.line_start.saturating_sub(1) as u32,
-                            character: span.column_start.saturating_sub(1) as u32,
-                        },
-                        end: lsp_types::Position {
-                            line: span.line_end.saturating_sub(1) as u32,
-                            character: span.column_end.saturating_sub(1) as u32,
+        let result = if as_list {
+            let entries: Vec<serde_json::Value> = hints
+                .iter()
+                .filter_map(|hint| {
+                    let line = lines.get((hint.position.line + 1).checked_sub(first)? as usize)?;
+                    let offset = utf16_to_byte_offset(line, hint.position.character);
+                    Some(serde_json::json!({
+                        "line": hint.position.line + 1,
+                        "column": line[..offset].chars().count() + 1,
+                        "kind": match hint.kind {
+                            Some(lsp_types::InlayHintKind::TYPE) => Some("type"),
+                            Some(lsp_types::InlayHintKind::PARAMETER) => Some("parameter"),
+                            _ => None,
                         },
-                    };
+                        "label": inlay_hint_label(hint).trim(),
+                    }))
+                })
+                .collect();
+            CallToolResult::success(vec![Content::json(serde_json::json!({
+                "file_path": file_path.display().to_string(),
+                "hints": entries,
+            }))?])
+        } else {
+            CallToolResult::success(vec![Content::text(format!(
+                "### {}\n```rust\n{}```",
+                file_path.display(),
+                render_inlay_hints(lines, first - 1, &hints)
+            ))])
+        };
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
 
-                    let available_fixes = if let Ok(Some(actions)) =
-                        project.lsp.code_actions(&absolute_path, range).await
-                    {
-                        actions
-                            .into_iter()
-                            .filter_map(|action_or_cmd| {
-                                if let lsp_types::CodeActionOrCommand::CodeAction(action) =
-                                    action_or_cmd
-                                {
-                                    Some(Fix {
-                                        title: action.title,
-                                        kind: action.kind,
-                                        edit_to_apply: action.edit,
-                                    })
-                                } else {
-                                    None
-                                }
-                            })
-                            .collect()
-                    } else {
-                        vec![]
-                    };
+    #[tool(
+        name = "completions",
+        description = "Lists what rust-analyzer would complete at a position: methods and fields of a value after `.`, items of a path after `::`, names in scope. Can run against unsaved file contents. Results are ranked and truncated; the top items include documentation."
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn completions(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The file to complete in (absolute, project-relative or a file name).")]
+        file: String,
+        #[tool(param)]
+        #[schemars(description = "1-based line of the cursor.")]
+        line: u32,
+        #[tool(param)]
+        #[schemars(description = "1-based column of the cursor. Defaults to the end of the line.")]
+        column: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "Optional unsaved contents of the whole file to complete against instead of the file on disk, e.g. with `value.` typed at the cursor.")]
+        content: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Maximum number of items to return. Default 30.")]
+        max_results: Option<usize>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
 
-                    results.push(DiagnosticWithFixes {
-                        file_path: span.file_name.clone(),
-                        severity: diag.level.clone(),
-                        message: diag.rendered.clone(),
-                        line: span.line_start,
-                        character: span.column_start,
-                        available_fixes,
-                    });
-                }
-            }
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+        if let Err(not_ready) = self.wait_until_ready(&project).await {
+            return Ok(not_ready);
+        }
+
+        let (file_path, position) = match resolve_cursor(&project, &file, line, column, content.as_deref()).await {
+            Ok(cursor) => cursor,
+            Err(e) => return Ok(error_response(&e)),
+        };
+
+        let _overlay = match open_overlay(&project, &file_path, content).await {
+            Ok(overlay) => overlay,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let mut items = project
+            .lsp
+            .completions(&file_path, position)
+            .await
+            .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
+        // rust-analyzer encodes its relevance ranking in sort_text; preselected items go first.
+        items.sort_by(|a, b| {
+            (a.preselect != Some(true), a.sort_text.as_ref().unwrap_or(&a.label))
+                .cmp(&(b.preselect != Some(true), b.sort_text.as_ref().unwrap_or(&b.label)))
+        });
+        let total = items.len();
+        items.truncate(max_results.unwrap_or(30));
+        let mut resolved = Vec::with_capacity(items.len());
+        for (i, item) in items.into_iter().enumerate() {
+            if i < RESOLVED_COMPLETIONS && item.documentation.is_none() {
+                resolved.push(project.lsp.resolve_completion(item.clone()).await.unwrap_or(item));
+            } else {
+                resolved.push(item);
+            }
+        }
+        let items = resolved;
+
+        let entries: Vec<serde_json::Value> = items
+            .iter()
+            .map(|item| {
+                let details = item.label_details.as_ref();
+                serde_json::json!({
+                    "label": format!(
+                        "{}{}",
+                        item.label,
+                        details.and_then(|d| d.detail.as_deref()).unwrap_or_default()
+                    ),
+                    "kind": item.kind.map(|kind| format!("{:?}", kind)),
+                    "detail": item.detail.as_deref().or(details.and_then(|d| d.description.as_deref())),
+                    "documentation": item.documentation.as_ref().map(documentation_text),
+                })
+            })
+            .collect();
+        let result_json = serde_json::json!({
+            "file_path": file_path.display().to_string(),
+            "total": total,
+            "truncated": total > entries.len(),
+            "items": entries,
+        });
+
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "signature_help",
+        description = "Shows the signature of the function or method being called at a position, with parameter names and types, the active parameter and documentation. Can run against unsaved file contents."
+    )]
+    async fn signature_help(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The file containing the call (absolute, project-relative or a file name).")]
+        file: String,
+        #[tool(param)]
+        #[schemars(description = "1-based line of the cursor, inside the call's parentheses.")]
+        line: u32,
+        #[tool(param)]
+        #[schemars(description = "1-based column of the cursor. Defaults to the end of the line.")]
+        column: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "Optional unsaved contents of the whole file to use instead of the file on disk, e.g. with `foo(` typed at the cursor.")]
+        content: Option<String>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+        if let Err(not_ready) = self.wait_until_ready(&project).await {
+            return Ok(not_ready);
+        }
+
+        let (file_path, position) = match resolve_cursor(&project, &file, line, column, content.as_deref()).await {
+            Ok(cursor) => cursor,
+            Err(e) => return Ok(error_response(&e)),
+        };
+
+        let _overlay = match open_overlay(&project, &file_path, content).await {
+            Ok(overlay) => overlay,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let help = project.lsp.signature_help(&file_path, position).await;
+        let Some(help) = help.map_err(|e| rmcp::Error::internal_error(e.to_string(), None))? else {
+            return Ok(error_response("No call found at this position. Place the cursor inside the argument list."));
+        };
+
+        let signatures: Vec<serde_json::Value> = help
+            .signatures
+            .iter()
+            .map(|signature| {
+                let parameters: Vec<serde_json::Value> = signature
+                    .parameters
+                    .iter()
+                    .flatten()
+                    .map(|parameter| {
+                        let label = match &parameter.label {
+                            lsp_types::ParameterLabel::Simple(label) => label.clone(),
+                            lsp_types::ParameterLabel::LabelOffsets([start, end]) => signature.label
+                                [utf16_to_byte_offset(&signature.label, *start)..utf16_to_byte_offset(&signature.label, *end)]
+                                .to_string(),
+                        };
+                        serde_json::json!({
+                            "label": label,
+                            "documentation": parameter.documentation.as_ref().map(documentation_text),
+                        })
+                    })
+                    .collect();
+                serde_json::json!({
+                    "label": signature.label,
+                    "documentation": signature.documentation.as_ref().map(documentation_text),
+                    "parameters": parameters,
+                    "active_parameter": signature.active_parameter.or(help.active_parameter),
+                })
+            })
+            .collect();
+        let result_json = serde_json::json!({
+            "file_path": file_path.display().to_string(),
+            "active_signature": help.active_signature,
+            "signatures": signatures,
+        });
+
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "check_unsaved_file",
+        description = "Type-checks unsaved contents of a file with rust-analyzer without writing it, to see whether a planned edit would compile before making it. Reports rust-analyzer's own diagnostics (unresolved names, type mismatches, missing fields, ...), not a full cargo build."
+    )]
+    async fn check_unsaved_file(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The file the contents belong to (absolute, project-relative or a file name). It must already exist and be part of a crate.")]
+        file: String,
+        #[tool(param)]
+        #[schemars(description = "The complete unsaved contents of the file.")]
+        content: String,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+        if let Err(not_ready) = self.wait_until_ready(&project).await {
+            return Ok(not_ready);
+        }
+
+        let file_path = match resolve_file_path(&project, &file).await {
+            Ok(path) => path,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let overlay = match open_overlay_with(&project, &file_path, content.clone()).await {
+            Ok(overlay) => overlay,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let (diagnostics, settled) = match project
+            .lsp
+            .overlay_diagnostics(&file_path, &overlay, UNSAVED_DIAGNOSTICS_TIMEOUT)
+            .await
+        {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => return Ok(error_response(&e.to_string())),
+        };
+        drop(overlay);
+
+        let lines: Vec<&str> = content.lines().collect();
+        let column = |position: &lsp_types::Position| {
+            lines.get(position.line as usize).map_or(position.character as usize + 1, |line| {
+                line[..utf16_to_byte_offset(line, position.character)].chars().count() + 1
+            })
+        };
+        // cargo check results published for the file on disk don't describe these contents.
+        let diagnostics: Vec<serde_json::Value> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.source.as_deref() == Some("rust-analyzer"))
+            .map(|diagnostic| {
+                serde_json::json!({
+                    "severity": match diagnostic.severity {
+                        Some(lsp_types::DiagnosticSeverity::ERROR) => "error",
+                        Some(lsp_types::DiagnosticSeverity::WARNING) => "warning",
+                        Some(lsp_types::DiagnosticSeverity::INFORMATION) => "info",
+                        _ => "hint",
+                    },
+                    "code": diagnostic.code.as_ref().map(|code| match code {
+                        lsp_types::NumberOrString::Number(n) => n.to_string(),
+                        lsp_types::NumberOrString::String(s) => s.clone(),
+                    }),
+                    "message": diagnostic.message,
+                    "line": diagnostic.range.start.line + 1,
+                    "column": column(&diagnostic.range.start),
+                    "end_line": diagnostic.range.end.line + 1,
+                    "end_column": column(&diagnostic.range.end),
+                })
+            })
+            .collect();
+        let errors = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic["severity"] == "error")
+            .count();
+
+        let result_json = serde_json::json!({
+            "file_path": file_path.display().to_string(),
+            "errors": errors,
+            "diagnostics": diagnostics,
+            // rust-analyzer publishes nothing when the diagnostics didn't change.
+            "settled": settled,
+        });
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    // --- Project Health ---
+    #[tool(
+        name = "check_project",
+        description = "Checks the project for errors/warnings. Returns human-readable messages by default, or structured diagnostics with fixes when include_fixes=true. With since_last=true, only reports what changed since the previous check."
+    )]
+    async fn check_project(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project to check for errors and warnings. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Whether to include structured diagnostics with available fixes. Default is false for human-readable output.")]
+        include_fixes: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Report new, resolved and unchanged counts relative to the previous check_project of this project, listing only the new and resolved diagnostics. Default is false.")]
+        since_last: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Attach the opening of rustc's explanation for each distinct error code (E0502, ...) among the reported diagnostics. Default is false.")]
+        explain_codes: Option<bool>,
+    ) -> Result<CallToolResult, rmcp::Error> {        
+        let project_name = self.get_project_name(project_name).await?;
+        
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let include_fixes = include_fixes.unwrap_or(false);
+
+        let report = project
+            .cargo_remote
+            .check_structured()
+            .await
+            .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
+        // A failed build always carries an error, so an empty list means the build passed.
+        let diagnostics = report.diagnostics;
+        let previous = project
+            .last_check
+            .lock()
+            .unwrap()
+            .replace(diagnostics.clone());
+
+        let (mut result, reported) = if since_last.unwrap_or(false) {
+            let first_check = previous.is_none();
+            let previous = previous.unwrap_or_default();
+            let diff = DiagnosticDiff::new(&previous, &diagnostics);
+            let new = diagnostics_with_fixes(&project, &diff.new, include_fixes).await;
+            let resolved = diagnostics_with_fixes(&project, &diff.resolved, false).await;
+            let result_json = serde_json::json!({
+                "first_check": first_check,
+                "build_succeeded": report.success,
+                "new_count": diff.new.len(),
+                "resolved_count": diff.resolved.len(),
+                "unchanged_count": diff.unchanged,
+                "new": new,
+                "resolved": resolved,
+            });
+
+            let result = CallToolResult::success(vec![Content::json(result_json)?]);
+            (result, diff.new.into_iter().cloned().collect())
+        } else if include_fixes {
+            // Return structured diagnostics with fixes
+            let reported: Vec<&CompilerMessage> = diagnostics.iter().collect();
+            let results = diagnostics_with_fixes(&project, &reported, true).await;
 
             if results.is_empty() {
                 let result = CallToolResult::success(vec![Content::text(
@@ -626,50 +1615,189 @@ impl DevToolsServer {
                 rmcp::Error::internal_error(format!("Failed to serialize results: {}", e), None)
             })?;
 
-            let result = CallToolResult::success(vec![Content::json(result_json)?]);
-            notify_resp(&self.context, &result, &project_path).await;
-            Ok(result)
+            (CallToolResult::success(vec![Content::json(result_json)?]), diagnostics)
         } else {
             // Return human-readable messages
-            let rendered_messages = project
-                .cargo_remote
-                .check_rendered()
-                .await
-                .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
-
-            if rendered_messages.is_empty() {
+            if diagnostics.is_empty() {
                 return Ok(CallToolResult::success(vec![Content::text(
                     "Project check passed. No errors or warnings.".to_string(),
                 )]));
             }
 
-            let result =
-                CallToolResult::success(rendered_messages.into_iter().map(Content::text).collect());
+            let result = CallToolResult::success(
+                diagnostics
+                    .iter()
+                    .map(|diagnostic| Content::text(diagnostic.rendered.clone()))
+                    .collect(),
+            );
+            (result, diagnostics)
+        };
+
+        if explain_codes.unwrap_or(false) {
+            let explanations = error_code_excerpts(&project, &reported).await;
+            if !explanations.is_empty() {
+                result
+                    .content
+                    .push(Content::json(serde_json::json!({ "explanations": explanations }))?);
+            }
+        }
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "clippy_project",
+        description = "Runs `cargo clippy` on the project and returns lint results grouped by lint name with counts. Lint levels can be overridden per call."
+    )]
+    async fn clippy_project(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project to lint. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional lints or lint groups to allow for this run (e.g., 'clippy::too_many_arguments').")]
+        allow: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Optional lints or lint groups to warn on for this run (e.g., 'clippy::pedantic').")]
+        warn: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Optional lints or lint groups to deny for this run (e.g., 'clippy::unwrap_used').")]
+        deny: Option<Vec<String>>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let overrides = LintOverrides {
+            allow: allow.unwrap_or_default(),
+            warn: warn.unwrap_or_default(),
+            deny: deny.unwrap_or_default(),
+        };
+        let report = project
+            .cargo_remote
+            .clippy_structured(&overrides)
+            .await
+            .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
+        let diagnostics = report.diagnostics;
+
+        if diagnostics.is_empty() {
+            let result = CallToolResult::success(vec![Content::text(
+                "Clippy passed. No lints found.".to_string(),
+            )]);
             notify_resp(&self.context, &result, &project_path).await;
-            Ok(result)
+            return Ok(result);
+        }
+
+        let total = diagnostics.len();
+        let mut groups: Vec<LintGroup> = Vec::new();
+        for diag in diagnostics {
+            let lint = diag.code_name().unwrap_or("uncategorized").to_string();
+            let occurrence = diag.spans.iter().find(|s| s.is_primary).map(|span| {
+                LintOccurrence {
+                    file_path: span.file_name.clone(),
+                    line: span.line_start,
+                    character: span.column_start,
+                    message: diag.message.clone(),
+                }
+            });
+            let group = match groups.iter_mut().position(|g| g.lint == lint) {
+                Some(index) => &mut groups[index],
+                None => {
+                    groups.push(LintGroup {
+                        lint,
+                        level: diag.level.clone(),
+                        count: 0,
+                        occurrences: Vec::new(),
+                    });
+                    groups.last_mut().unwrap()
+                }
+            };
+            group.count += 1;
+            group.occurrences.extend(occurrence);
         }
+        groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.lint.cmp(&b.lint)));
+
+        let result_json = serde_json::json!({
+            "build_succeeded": report.success,
+            "total": total,
+            "lint_count": groups.len(),
+            "lints": groups,
+        });
+
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "explain_error",
+        description = "Explains a compiler error code such as E0502 or E0277 with rustc's long-form explanation, including erroneous and corrected code examples. Works offline."
+    )]
+    async fn explain_error(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project whose toolchain to ask. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The error code, e.g. 'E0502'. The leading 'E' may be omitted.")]
+        code: String,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let explanation = match project.cargo_remote.explain(&code).await {
+            Ok(explanation) => explanation,
+            Err(e) => return Ok(error_response(&e.to_string())),
+        };
+
+        let result = CallToolResult::success(vec![Content::text(explanation.to_string())]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
     }
 
     // apply_workspace_edit tool removed - functionality integrated into confirm_operation
 
     #[tool(
         name = "rename_symbol",
-        description = "Renames a symbol across the entire project. Can either execute immediately or return a preview for confirmation."
+        description = "Renames a symbol across the entire project. Can either execute immediately or return a preview for confirmation. Instead of a name, a file position can be given to rename locals and closure parameters."
     )]
+    #[allow(clippy::too_many_arguments)]
     async fn rename_symbol(
         &self,
         #[tool(param)]
         #[schemars(description = "The name of the project containing the symbol to rename. If not provided, uses the most recently used project.")]
         project_name: Option<String>,
         #[tool(param)]
-        #[schemars(description = "The name of the symbol to rename (e.g., function name, struct name, variable name).")]
-        symbol_name: String,
+        #[schemars(description = "The symbol to rename: a bare name (e.g., function name, struct name) or a Rust path such as `Config::load` to pick one among several with the same name. Omit it when giving file and line.")]
+        symbol_name: Option<String>,
         #[tool(param)]
         #[schemars(description = "The new name for the symbol.")]
         new_name: String,
         #[tool(param)]
-        #[schemars(description = "Optional file path hint to help locate the symbol more efficiently when there are multiple symbols with the same name.")]
-        file_hint: Option<String>,
+        #[schemars(description = "Instead of symbol_name: the file containing the position to look at (absolute, project-relative or a file name).")]
+        file: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "1-based line of the position, used with file.")]
+        line: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "1-based column of the position, used with file and line.")]
+        column: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = "Instead of column: a snippet on the line that starts at the symbol, e.g. 'count' or 'count +='.")]
+        text: Option<String>,
         #[tool(param)]
         #[schemars(description = "If true, executes the rename immediately. If false, creates a preview that can be executed later with execute_code_action.")]
         execute_immediately: Option<bool>,
@@ -685,34 +1813,30 @@ impl DevToolsServer {
             )));
         };
         let project = self.context.get_project(&project_path).await.unwrap();
+        if let Err(not_ready) = self.wait_until_ready(&project).await {
+            return Ok(not_ready);
+        }
         
-        // Use resolve_symbol_in_project to find the symbol location
-        let symbol_info = match crate::mcp::utils::resolve_symbol_in_project(
-            &project,
-            &symbol_name,
-            file_hint.as_deref(),
-        ).await {
-            Ok(info) => info,
+        let args = TargetArgs {
+            symbol_name,
+            file,
+            line,
+            column,
+            text,
+            content: None,
+        };
+        let target = match resolve_symbol_target(&project, &args, &SymbolQuery::default()).await {
+            Ok(target) => target,
             Err(e) => {
-                return Ok(error_response(&format!(
-                    "Failed to locate symbol '{}': {}",
-                    symbol_name, e
-                )));
+                return Ok(error_response(&format!("Failed to locate symbol: {}", e)));
             }
         };
-        
-        // Extract position from symbol location
-        let absolute_path = match symbol_info.location.uri.to_file_path() {
+        let absolute_path = match target.file() {
             Ok(path) => path,
-            Err(_) => {
-                return Ok(error_response(&format!(
-                    "Invalid file path for symbol '{}'",
-                    symbol_name
-                )));
-            }
+            Err(e) => return Ok(error_response(&e)),
         };
-        
-        let position = symbol_info.location.range.start;
+        let position = target.position();
+        let symbol_name = target.name().to_string();
 
         let edit = project.lsp.rename(&absolute_path, position, new_name.clone()).await
             .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?
@@ -723,7 +1847,8 @@ impl DevToolsServer {
         if execute_now {
             // Execute immediately
             match crate::mcp::utils::apply_workspace_edit(&edit) {
-                Ok(()) => {
+                Ok(touched_files) => {
+                    let formatted = self.format_edited_files(&project_path, touched_files).await;
                     let result_json = serde_json::json!({
                         "status": "completed",
                         "operation": "rename",
@@ -733,6 +1858,7 @@ impl DevToolsServer {
                         "files_affected": edit.changes.as_ref().map(|changes| {
                             changes.keys().map(|uri| uri.to_string()).collect::<Vec<_>>()
                         }).unwrap_or_default(),
+                        "formatted": formatted,
                         "message": format!("✓ Successfully renamed '{}' to '{}'", symbol_name, new_name)
                     });
                     
@@ -883,33 +2009,940 @@ impl DevToolsServer {
     }
     
     #[tool(
-        name = "list_code_actions",
-        description = "List all available code actions that can be executed."
+        name = "bench_project",
+        description = "Runs `cargo bench` (libtest or criterion benches) and returns the timings as numbers. Results can be saved as a named baseline and compared against one, with percentage changes and regressions flagged beyond a noise threshold."
     )]
-    async fn list_code_actions(&self) -> Result<CallToolResult, rmcp::Error> {
-        if self.code_actions.is_empty() {
-            return Ok(CallToolResult::success(vec![Content::text(
-                "No code actions available.".to_string()
-            )]));
-        }
-        
-        let actions_list: Vec<serde_json::Value> = self.code_actions.iter().map(|entry| {
-            let action = entry.value();
-            serde_json::json!({
-                "id": action.id,
-                "title": action.title,
-                "description": action.description,
-                "kind": action.kind,
-                "project_name": action.project_name
-            })
-        }).collect();
-        
-        let result_json = serde_json::json!({
-            "code_actions": actions_list,
-            "count": self.code_actions.len()
-        });
-        
-        Ok(CallToolResult::success(vec![Content::text(
+    #[allow(clippy::too_many_arguments)]
+    async fn bench_project(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project to benchmark. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional package to benchmark, for workspaces.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional bench targets to run (names of files in `benches/`). If not provided, all are run.")]
+        benches: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Optional filter passed to the bench harness; only benchmarks whose name contains it are run.")]
+        filter: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Save the results as a baseline with this name, replacing any baseline of the same name.")]
+        save_baseline: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Compare the results against the baseline with this name.")]
+        compare_to: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Changes smaller than this percentage count as unchanged. Defaults to 5.")]
+        noise_threshold_percent: Option<f64>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        // Check the baseline names first so a typo doesn't cost a whole bench run.
+        if let Some(Err(e)) = save_baseline.as_deref().map(BaselineStore::validate_name) {
+            return Ok(error_response(&e.to_string()));
+        }
+        let store = project.cargo_remote.bench_baselines();
+        let baseline = match compare_to.as_deref().map(|name| store.load(name)).transpose() {
+            Ok(baseline) => baseline,
+            Err(e) => return Ok(error_response(&e.to_string())),
+        };
+
+        let outcome = project
+            .cargo_remote
+            .bench(package.as_deref(), &benches.unwrap_or_default(), filter.as_deref())
+            .await
+            .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
+        let results = match outcome {
+            BenchOutcome::Finished(results) => results,
+            BenchOutcome::Failed { report, stderr, output } => {
+                let mut contents: Vec<Content> = report
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.level == "error")
+                    .map(|diagnostic| Content::text(diagnostic.rendered.clone()))
+                    .collect();
+                // Where a panicking benchmark's message and name end up.
+                if !stderr.trim().is_empty() {
+                    contents.push(Content::text(format!("End of cargo's stderr:\n{}", stderr)));
+                }
+                if !output.trim().is_empty() {
+                    contents.push(Content::text(format!("End of the bench output:\n{}", output)));
+                }
+                let result = CallToolResult::error(contents);
+                notify_resp(&self.context, &result, &project_path).await;
+                return Ok(result);
+            }
+        };
+        if results.is_empty() {
+            let result = error_response(
+                "cargo bench succeeded but printed no benchmark timings. Check that the filter matches and the benches use libtest's #[bench] or criterion.",
+            );
+            notify_resp(&self.context, &result, &project_path).await;
+            return Ok(result);
+        }
+
+        let mut result_json = serde_json::json!({ "results": results });
+        if let Some(baseline) = baseline {
+            let threshold = noise_threshold_percent.unwrap_or(DEFAULT_BENCH_NOISE_THRESHOLD);
+            let comparisons = compare(&baseline.results, &results, threshold);
+            let count = |verdict| comparisons.iter().filter(|c| c.verdict == verdict).count();
+            result_json["comparison"] = serde_json::json!({
+                "baseline": baseline.name,
+                "baseline_saved_at": baseline.saved_at,
+                "threshold_percent": threshold,
+                "regressed": count(Verdict::Regressed),
+                "improved": count(Verdict::Improved),
+                "unchanged": count(Verdict::Unchanged),
+                "benches": comparisons,
+            });
+        }
+        if let Some(name) = save_baseline {
+            match store.save(&name, &results) {
+                Ok(_) => result_json["saved_baseline"] = serde_json::json!(name),
+                // Don't throw away a finished run because the baseline couldn't be written.
+                Err(e) => result_json["save_error"] = serde_json::json!(format!("{:#}", e)),
+            }
+        }
+
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "crate_docs",
+        description = "Looks up the API documentation of an item in a dependency or workspace crate, e.g. 'dashmap::DashMap' to list its methods or 'flume::Sender::send_async' for one method. Built offline from local sources with rustdoc. Use this instead of guessing a dependency's API."
+    )]
+    async fn crate_docs(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project whose dependencies to look in. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Path of the item starting with the crate name, e.g. 'tokio::sync::mpsc', 'dashmap::DashMap' or 'flume::Sender::send_async'. A bare crate name lists the crate root.")]
+        path: String,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let segments: Vec<&str> = path
+            .split("::")
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let Some((crate_name, item_path)) = segments.split_first() else {
+            return Ok(error_response("The path is empty."));
+        };
+
+        let (package, docs) = match project.cargo_remote.crate_docs(crate_name).await {
+            Ok(loaded) => loaded,
+            Err(e) => return Ok(error_response(&format!("{:#}", e))),
+        };
+        let item = match docs.lookup(&crate_name.replace('-', "_"), item_path) {
+            Ok(item) => item,
+            Err(e) => return Ok(error_response(&e.to_string())),
+        };
+
+        let result_json = serde_json::json!({
+            "package": package.name,
+            "version": package.version,
+            "item": item,
+        });
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "run_binary",
+        description = "Builds and runs a binary target of the project with the given arguments, environment and stdin. Returns the exit status, stdout and stderr (long output keeps its beginning and end). The program is killed when it exceeds the timeout or the call is cancelled."
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn run_binary(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project to run. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The binary target to run. Can be omitted when there is only one.")]
+        bin: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional package containing the binary, for workspaces.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Command-line arguments passed to the program.")]
+        args: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Environment variables set for the program, on top of the project's configured cargo environment.")]
+        env: Option<HashMap<String, String>>,
+        #[tool(param)]
+        #[schemars(description = "Text written to the program's stdin. If not provided, stdin is empty.")]
+        stdin: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Working directory relative to the project root; it must lie inside the project. Defaults to the project root.")]
+        cwd: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Seconds the program may run before it is killed, not counting the build. Defaults to 60.")]
+        timeout_secs: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Build and run with the release profile. Defaults to false.")]
+        release: Option<bool>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let options = RunOptions {
+            package,
+            release: release.unwrap_or(false),
+            args: args.unwrap_or_default(),
+            env: env.unwrap_or_default().into_iter().collect(),
+            stdin,
+            cwd: cwd.map(PathBuf::from),
+            timeout: Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_RUN_TIMEOUT_SECS)),
+        };
+        self.run_target(project_name, RunTarget::Bin(bin), options).await
+    }
+
+    #[tool(
+        name = "run_example",
+        description = "Builds and runs an example (from `examples/`) with the given arguments, environment and stdin. Returns the exit status, stdout and stderr (long output keeps its beginning and end). The program is killed when it exceeds the timeout or the call is cancelled."
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn run_example(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project to run. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The example to run.")]
+        example: String,
+        #[tool(param)]
+        #[schemars(description = "Optional package containing the example, for workspaces.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Command-line arguments passed to the program.")]
+        args: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Environment variables set for the program, on top of the project's configured cargo environment.")]
+        env: Option<HashMap<String, String>>,
+        #[tool(param)]
+        #[schemars(description = "Text written to the program's stdin. If not provided, stdin is empty.")]
+        stdin: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Working directory relative to the project root; it must lie inside the project. Defaults to the project root.")]
+        cwd: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Seconds the program may run before it is killed, not counting the build. Defaults to 60.")]
+        timeout_secs: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Build and run with the release profile. Defaults to false.")]
+        release: Option<bool>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let options = RunOptions {
+            package,
+            release: release.unwrap_or(false),
+            args: args.unwrap_or_default(),
+            env: env.unwrap_or_default().into_iter().collect(),
+            stdin,
+            cwd: cwd.map(PathBuf::from),
+            timeout: Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_RUN_TIMEOUT_SECS)),
+        };
+        self.run_target(project_name, RunTarget::Example(example), options).await
+    }
+
+    #[tool(
+        name = "format",
+        description = "Checks or applies rustfmt formatting for the whole project, a single package or specific files. Returns the formatting diff."
+    )]
+    async fn format(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project to format. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional package name to format instead of the whole workspace.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional list of files (relative to the project root or absolute) to format instead of the whole workspace.")]
+        files: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "If true, writes the formatting changes to disk. If false (default), only returns the diff.")]
+        apply: Option<bool>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let target = match (package, files) {
+            (Some(_), Some(_)) => {
+                return Ok(error_response(
+                    "Provide either 'package' or 'files', not both.",
+                ));
+            }
+            (Some(package), None) => FormatTarget::Package(package),
+            (None, Some(files)) => FormatTarget::Files(files.into_iter().map(PathBuf::from).collect()),
+            (None, None) => FormatTarget::Project,
+        };
+
+        let outcome = match project
+            .cargo_remote
+            .format(&target, apply.unwrap_or(false))
+            .await
+        {
+            Ok(outcome) => outcome,
+            Err(e) => return Ok(error_response(&e.to_string())),
+        };
+
+        let result = if outcome.is_formatted {
+            CallToolResult::success(vec![Content::text(
+                "Already formatted. No changes needed.".to_string(),
+            )])
+        } else {
+            let status = if outcome.applied {
+                "Applied formatting changes:"
+            } else {
+                "Formatting changes needed (not applied):"
+            };
+            CallToolResult::success(vec![
+                Content::text(status.to_string()),
+                Content::text(format!("```diff\n{}\n```", outcome.diff.trim_end())),
+            ])
+        };
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "workspace_info",
+        description = "Describes the Cargo workspace from `cargo metadata`: members, targets, features and dependencies. Can also look up a single package or the package a file belongs to."
+    )]
+    async fn workspace_info(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional package name. Returns its targets, feature definitions, enabled features and dependencies.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional file path (relative to the project root or absolute). Returns the package that owns the file.")]
+        file_path: Option<String>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let metadata = match project.cargo_remote.metadata().await {
+            Ok(metadata) => metadata,
+            Err(e) => return Ok(error_response(&format!("Failed to load workspace metadata: {}", e))),
+        };
+
+        let result_json = match (package, file_path) {
+            (Some(_), Some(_)) => {
+                return Ok(error_response(
+                    "Provide either 'package' or 'file_path', not both.",
+                ));
+            }
+            (Some(name), None) => {
+                let Some(package) = metadata.package_by_name(&name) else {
+                    return Ok(error_response(&format!(
+                        "Package '{}' not found in the workspace or its dependencies.",
+                        name
+                    )));
+                };
+                package_details(&metadata, package)
+            }
+            (None, Some(file_path)) => {
+                let path = project_path.join(&file_path);
+                let path = path.canonicalize().unwrap_or(path);
+                let Some(package) = metadata.package_for_file(&path) else {
+                    return Ok(error_response(&format!(
+                        "'{}' does not belong to any package in the workspace.",
+                        file_path
+                    )));
+                };
+                serde_json::json!({
+                    "file_path": file_path,
+                    "package": package_details(&metadata, package),
+                })
+            }
+            (None, None) => workspace_summary(&metadata),
+        };
+
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "dependency_tree",
+        description = "Shows the resolved dependency graph like `cargo tree`. Supports inverted 'why is this crate here' queries, duplicate-version detection, a depth limit and filtering by dependency kind."
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn dependency_tree(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional package to start from. Defaults to every workspace member.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional package name to explain: shows the chains of packages that pull it in, up to the workspace members.")]
+        invert: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "If true, lists packages resolved in more than one version together with what pulls in each version.")]
+        duplicates: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Optional maximum depth of the tree.")]
+        depth: Option<usize>,
+        #[tool(param)]
+        #[schemars(description = "Optional dependency kinds to follow: any of 'normal', 'dev', 'build'. Defaults to all.")]
+        kinds: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Output format: 'tree' (default, indented text) or 'json'.")]
+        format: Option<String>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let as_json = match format.as_deref() {
+            None | Some("tree") => false,
+            Some("json") => true,
+            Some(other) => {
+                return Ok(error_response(&format!(
+                    "Unknown format '{}'. Use 'tree' or 'json'.",
+                    other
+                )));
+            }
+        };
+        let kinds = match kinds {
+            None => DependencyKind::ALL.to_vec(),
+            Some(kinds) => {
+                let mut parsed = Vec::new();
+                for kind in kinds {
+                    let Some(kind_value) = DependencyKind::parse(&kind) else {
+                        return Ok(error_response(&format!(
+                            "Unknown dependency kind '{}'. Use 'normal', 'dev' or 'build'.",
+                            kind
+                        )));
+                    };
+                    parsed.push(kind_value);
+                }
+                parsed
+            }
+        };
+        let duplicates = duplicates.unwrap_or(false);
+        if [package.is_some(), invert.is_some(), duplicates].iter().filter(|set| **set).count() > 1 {
+            return Ok(error_response(
+                "Provide only one of 'package', 'invert' or 'duplicates'.",
+            ));
+        }
+
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let metadata = match project.cargo_remote.metadata().await {
+            Ok(metadata) => metadata,
+            Err(e) => return Ok(error_response(&format!("Failed to load workspace metadata: {}", e))),
+        };
+        let options = TreeOptions {
+            kinds,
+            max_depth: depth,
+            invert: invert.is_some() || duplicates,
+        };
+        let trees = |packages: Vec<&Package>| -> Vec<DependencyNode> {
+            packages
+                .into_iter()
+                .filter_map(|package| metadata.dependency_tree(&package.id, &options))
+                .collect()
+        };
+
+        let result = if duplicates {
+            let groups: Vec<(String, Vec<DependencyNode>)> = metadata
+                .duplicate_packages()
+                .into_iter()
+                .map(|packages| (packages[0].name.clone(), trees(packages)))
+                .collect();
+            if groups.is_empty() {
+                CallToolResult::success(vec![Content::text(
+                    "No packages are resolved in more than one version.".to_string(),
+                )])
+            } else if as_json {
+                let duplicates_json: Vec<serde_json::Value> = groups
+                    .iter()
+                    .map(|(name, trees)| serde_json::json!({
+                        "name": name,
+                        "versions": trees.iter().map(|tree| &tree.version).collect::<Vec<_>>(),
+                        "dependents": trees,
+                    }))
+                    .collect();
+                CallToolResult::success(vec![Content::json(serde_json::json!({
+                    "duplicates": duplicates_json
+                }))?])
+            } else {
+                let text = groups
+                    .iter()
+                    .flat_map(|(_, trees)| trees.iter().map(DependencyNode::render))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                CallToolResult::success(vec![Content::text(text)])
+            }
+        } else {
+            let roots: Vec<&Package> = if let Some(name) = &invert {
+                metadata.resolved_packages().filter(|package| &package.name == name).collect()
+            } else if let Some(name) = &package {
+                metadata
+                    .resolved_packages()
+                    .filter(|candidate| &candidate.name == name)
+                    .take(1)
+                    .collect()
+            } else {
+                metadata.workspace_packages().collect()
+            };
+            if roots.is_empty() {
+                let name = invert.or(package).unwrap_or_default();
+                error_response(&format!(
+                    "Package '{}' is not part of the resolved dependency graph.",
+                    name
+                ))
+            } else {
+                let trees = trees(roots);
+                if as_json {
+                    CallToolResult::success(vec![Content::json(serde_json::json!({
+                        "inverted": options.invert,
+                        "trees": trees
+                    }))?])
+                } else {
+                    let text = trees
+                        .iter()
+                        .map(DependencyNode::render)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    CallToolResult::success(vec![Content::text(text)])
+                }
+            }
+        };
+
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "add_dependency",
+        description = "Adds a dependency to a package's Cargo.toml, preserving its formatting. Supports dev/build and target-specific tables and workspace-inherited dependencies. Without a version, uses the newest version in the local registry cache so it resolves offline."
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn add_dependency(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The workspace member to edit. Required when the workspace has several members and no root package.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The crate to add, e.g. 'serde'.")]
+        name: String,
+        #[tool(param)]
+        #[schemars(description = "Optional version requirement, e.g. '1.0'. Defaults to the newest cached version.")]
+        version: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional path for a path dependency, as written into the manifest.")]
+        path: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional features to enable.")]
+        features: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Whether the dependency is optional. Defaults to false.")]
+        optional: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Optional: set to false to disable the crate's default features.")]
+        default_features: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Dependency kind: 'normal' (default), 'dev' or 'build'.")]
+        kind: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional platform cfg or target triple for a target-specific table, e.g. 'cfg(unix)'.")]
+        target: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Inherit from [workspace.dependencies], adding it there if missing. Defaults to true when the workspace already declares the crate.")]
+        workspace: Option<bool>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let table = match dependency_table(kind, target) {
+            Ok(table) => table,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let metadata = match project.cargo_remote.metadata().await {
+            Ok(metadata) => metadata,
+            Err(e) => return Ok(error_response(&format!("Failed to load workspace metadata: {}", e))),
+        };
+        let member_path = match member_manifest(&metadata, package.as_deref()) {
+            Ok(path) => path,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let root_path = metadata.workspace_root.join("Cargo.toml");
+
+        let open = |path: &PathBuf| ManifestEditor::open(path).map_err(|e| e.to_string());
+        let mut member = match open(&member_path) {
+            Ok(editor) => editor,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let mut root = if root_path != member_path {
+            match open(&root_path) {
+                Ok(editor) => Some(editor),
+                Err(e) => return Ok(error_response(&e)),
+            }
+        } else {
+            None
+        };
+
+        let declared_in_workspace = root.as_ref().unwrap_or(&member).has_workspace_dependency(&name);
+        let inherit = workspace.unwrap_or(declared_in_workspace);
+        let version = match (&version, &path) {
+            (None, None) if !(inherit && declared_in_workspace) => match latest_cached_version(&name) {
+                Some(version) => Some(version),
+                None => {
+                    return Ok(error_response(&format!(
+                        "No version of '{}' is in the local registry cache, so it can't be resolved offline. Pass 'version' after fetching the crate, or 'path'.",
+                        name
+                    )));
+                }
+            },
+            _ => version,
+        };
+
+        let mut root_edited = false;
+        if inherit && !declared_in_workspace {
+            let workspace_manifest = root.as_mut().unwrap_or(&mut member);
+            if let Err(e) = workspace_manifest.add_workspace_dependency(&name, version.as_deref(), path.as_deref()) {
+                return Ok(error_response(&e.to_string()));
+            }
+            root_edited = root.is_some();
+        }
+
+        let dependency = NewDependency {
+            name: name.clone(),
+            version: if inherit { None } else { version.clone() },
+            path: if inherit { None } else { path.clone() },
+            features: features.unwrap_or_default(),
+            optional: optional.unwrap_or(false),
+            default_features,
+            workspace: inherit,
+        };
+        if let Err(e) = member.add_dependency(&table, &dependency) {
+            return Ok(error_response(&e.to_string()));
+        }
+
+        // An inherited dependency gets its source from the workspace, whatever was passed in.
+        let (version, path) = if inherit {
+            let workspace_manifest = root.as_ref().unwrap_or(&member);
+            (
+                workspace_manifest.workspace_dependency_field(&name, "version"),
+                workspace_manifest.workspace_dependency_field(&name, "path"),
+            )
+        } else {
+            (version, path)
+        };
+
+        let mut editors = vec![&member];
+        if let (true, Some(root)) = (root_edited, &root) {
+            editors.push(root);
+        }
+        if let Err(e) = self.save_manifests(&project, &editors).await {
+            return Ok(error_response(&e));
+        }
+
+        let result_json = serde_json::json!({
+            "status": "added",
+            "dependency": name,
+            "version": version,
+            "path": path,
+            "table": table.to_string(),
+            "manifest": workspace_relative(&metadata, &member_path),
+            "workspace_inherited": inherit,
+            "features": dependency.features,
+        });
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "remove_dependency",
+        description = "Removes a dependency from a package's Cargo.toml, preserving its formatting, and drops feature entries that referred to it."
+    )]
+    async fn remove_dependency(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The workspace member to edit. Required when the workspace has several members and no root package.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The dependency key to remove, as written in the manifest.")]
+        name: String,
+        #[tool(param)]
+        #[schemars(description = "Dependency kind: 'normal' (default), 'dev' or 'build'.")]
+        kind: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional platform cfg or target triple of a target-specific table, e.g. 'cfg(unix)'.")]
+        target: Option<String>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let table = match dependency_table(kind, target) {
+            Ok(table) => table,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let metadata = match project.cargo_remote.metadata().await {
+            Ok(metadata) => metadata,
+            Err(e) => return Ok(error_response(&format!("Failed to load workspace metadata: {}", e))),
+        };
+        let member_path = match member_manifest(&metadata, package.as_deref()) {
+            Ok(path) => path,
+            Err(e) => return Ok(error_response(&e)),
+        };
+
+        let mut member = match ManifestEditor::open(&member_path) {
+            Ok(editor) => editor,
+            Err(e) => return Ok(error_response(&e.to_string())),
+        };
+        if let Err(e) = member.remove_dependency(&table, &name) {
+            return Ok(error_response(&e.to_string()));
+        }
+        if let Err(e) = self.save_manifests(&project, &[&member]).await {
+            return Ok(error_response(&e));
+        }
+
+        let result_json = serde_json::json!({
+            "status": "removed",
+            "dependency": name,
+            "table": table.to_string(),
+            "manifest": workspace_relative(&metadata, &member_path),
+        });
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "set_dependency_features",
+        description = "Enables or disables features of an existing dependency in a package's Cargo.toml, and optionally toggles its default features. Preserves formatting."
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn set_dependency_features(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "The name of the project. If not provided, uses the most recently used project.")]
+        project_name: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The workspace member to edit. Required when the workspace has several members and no root package.")]
+        package: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "The dependency key, as written in the manifest.")]
+        name: String,
+        #[tool(param)]
+        #[schemars(description = "Optional features to enable.")]
+        add_features: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Optional features to remove from the dependency's feature list.")]
+        remove_features: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = "Optional: set 'default-features' to this value. Not allowed for workspace-inherited dependencies.")]
+        default_features: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Dependency kind: 'normal' (default), 'dev' or 'build'.")]
+        kind: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Optional platform cfg or target triple of a target-specific table, e.g. 'cfg(unix)'.")]
+        target: Option<String>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let table = match dependency_table(kind, target) {
+            Ok(table) => table,
+            Err(e) => return Ok(error_response(&e)),
+        };
+        let project_name = self.get_project_name(project_name).await?;
+
+        let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+            return Ok(error_response(&format!(
+                "Project '{}' not found.",
+                project_name
+            )));
+        };
+        let project = self.context.get_project(&project_path).await.unwrap();
+
+        let metadata = match project.cargo_remote.metadata().await {
+            Ok(metadata) => metadata,
+            Err(e) => return Ok(error_response(&format!("Failed to load workspace metadata: {}", e))),
+        };
+        let member_path = match member_manifest(&metadata, package.as_deref()) {
+            Ok(path) => path,
+            Err(e) => return Ok(error_response(&e)),
+        };
+
+        let mut member = match ManifestEditor::open(&member_path) {
+            Ok(editor) => editor,
+            Err(e) => return Ok(error_response(&e.to_string())),
+        };
+        let features = match member.set_dependency_features(
+            &table,
+            &name,
+            &add_features.unwrap_or_default(),
+            &remove_features.unwrap_or_default(),
+            default_features,
+        ) {
+            Ok(features) => features,
+            Err(e) => return Ok(error_response(&e.to_string())),
+        };
+        if let Err(e) = self.save_manifests(&project, &[&member]).await {
+            return Ok(error_response(&e));
+        }
+
+        let result_json = serde_json::json!({
+            "status": "updated",
+            "dependency": name,
+            "table": table.to_string(),
+            "manifest": workspace_relative(&metadata, &member_path),
+            "features": features,
+            "default_features": default_features,
+        });
+        let result = CallToolResult::success(vec![Content::json(result_json)?]);
+        notify_resp(&self.context, &result, &project_path).await;
+        Ok(result)
+    }
+
+    #[tool(
+        name = "list_cargo_jobs",
+        description = "Lists the cargo commands queued or running for each project. Cargo runs one command per project at a time, and identical queued requests share a single run."
+    )]
+    async fn list_cargo_jobs(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Optional project name. If not provided, lists jobs for all projects.")]
+        project_name: Option<String>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let project_paths = match project_name {
+            Some(project_name) => {
+                let Some(project_path) = self.context.find_project_by_name(&project_name).await else {
+                    return Ok(error_response(&format!(
+                        "Project '{}' not found.",
+                        project_name
+                    )));
+                };
+                vec![project_path]
+            }
+            None => self
+                .context
+                .project_descriptions()
+                .await
+                .into_iter()
+                .map(|project| project.root)
+                .collect(),
+        };
+
+        let mut projects = Vec::new();
+        for project_path in project_paths {
+            let Some(project) = self.context.get_project(&project_path).await else {
+                continue;
+            };
+            let jobs = project.cargo_remote.jobs();
+            if jobs.is_empty() {
+                continue;
+            }
+            projects.push(serde_json::json!({
+                "project_name": project_path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown"),
+                "jobs": jobs,
+            }));
+        }
+
+        if projects.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "No cargo jobs queued or running.".to_string(),
+            )]));
+        }
+        Ok(CallToolResult::success(vec![Content::json(serde_json::json!({
+            "projects": projects
+        }))?]))
+    }
+
+    #[tool(
+        name = "list_code_actions",
+        description = "List all available code actions that can be executed."
+    )]
+    async fn list_code_actions(&self) -> Result<CallToolResult, rmcp::Error> {
+        if self.code_actions.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "No code actions available.".to_string()
+            )]));
+        }
+        
+        let actions_list: Vec<serde_json::Value> = self.code_actions.iter().map(|entry| {
+            let action = entry.value();
+            serde_json::json!({
+                "id": action.id,
+                "title": action.title,
+                "description": action.description,
+                "kind": action.kind,
+                "project_name": action.project_name
+            })
+        }).collect();
+        
+        let result_json = serde_json::json!({
+            "code_actions": actions_list,
+            "count": self.code_actions.len()
+        });
+        
+        Ok(CallToolResult::success(vec![Content::text(
             serde_json::to_string_pretty(&result_json).unwrap()
         )]))
     }
@@ -935,14 +2968,21 @@ impl DevToolsServer {
         };
         
         match crate::mcp::utils::apply_workspace_edit(&workspace_edit) {
-            Ok(()) => {
+            Ok(touched_files) => {
+                let project_path = self.context.find_project_by_name(&action.project_name).await;
+                let formatted = match &project_path {
+                    Some(project_path) => self.format_edited_files(project_path, touched_files).await,
+                    None => false,
+                };
                 let result = CallToolResult::success(vec![Content::text(format!(
-                    "✓ Executed code action '{}': {}",
-                    action.title, action.description
+                    "✓ Executed code action '{}': {}{}",
+                    action.title,
+                    action.description,
+                    if formatted { " (formatted)" } else { "" }
                 ))]);
                 
-                // Find project path for notification and auto-update
-                if let Some(project_path) = self.context.find_project_by_name(&action.project_name).await {
+                // Notify and auto-update the project the action belongs to
+                if let Some(project_path) = project_path {
                     notify_resp(&self.context, &result, &project_path).await;
                     
                     // 自动更新该项目的code actions，因为代码已被修改
@@ -963,8 +3003,49 @@ impl DevToolsServer {
     }
 }
 
-#[tool(tool_box)]
 impl ServerHandler for DevToolsServer {
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RmcpRequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, rmcp::Error> {
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: Self::tool_box().list(),
+        })
+    }
+
+    /// Dispatches a tool call, bounded by the project's timeout for that tool and by MCP
+    /// cancellation. Dropping the tool future kills any cargo process group it spawned and
+    /// cancels its in-flight LSP requests.
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RmcpRequestContext<RoleServer>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let tool = request.name.to_string();
+        let timeout = self.tool_timeout(&tool, request.arguments.as_ref()).await;
+        let ct = context.ct.clone();
+        let tool_context = ToolCallContext::new(self, request, context);
+
+        tokio::select! {
+            result = Self::tool_box().call(tool_context) => result,
+            _ = ct.cancelled() => {
+                tracing::info!("Tool call '{}' was cancelled", tool);
+                Ok(error_response(&format!("'{}' was cancelled.", tool)))
+            }
+            _ = tokio::time::sleep(timeout) => {
+                tracing::warn!("Tool call '{}' timed out after {:?}", tool, timeout);
+                Ok(error_response(&format!(
+                    "'{}' timed out after {} seconds and was stopped. Raise `timeouts.{}` in the project configuration if it needs longer.",
+                    tool,
+                    timeout.as_secs(),
+                    tool
+                )))
+            }
+        }
+    }
+
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::default(),
@@ -1025,6 +3106,36 @@ impl ServerHandler for DevToolsServer {
             });
         }
         
+        // Add workspace metadata for each project
+        for project in self.context.project_descriptions().await {
+            resources.push(Resource {
+                raw: RawResource {
+                    uri: format!("workspace://{}", project.name),
+                    name: format!("Workspace: {}", project.name),
+                    description: Some(format!(
+                        "Cargo workspace members, targets and features of {}",
+                        project.root.display()
+                    )),
+                    mime_type: Some("application/json".to_string()),
+                    size: None,
+                },
+                annotations: None,
+            });
+            resources.push(Resource {
+                raw: RawResource {
+                    uri: format!("lsp-stats://{}", project.name),
+                    name: format!("LSP request timings: {}", project.name),
+                    description: Some(format!(
+                        "In-flight, completed, failed and cancelled rust-analyzer requests and their durations, per LSP method, for {}",
+                        project.root.display()
+                    )),
+                    mime_type: Some("application/json".to_string()),
+                    size: None,
+                },
+                annotations: None,
+            });
+        }
+        
         Ok(ListResourcesResult {
             resources,
             next_cursor: None,
@@ -1074,6 +3185,47 @@ impl ServerHandler for DevToolsServer {
                     None,
                 ))
             }
+        } else if let Some(project_name) = request.uri.strip_prefix("workspace://") {
+            let Some(project_path) = self.context.find_project_by_name(project_name).await else {
+                return Err(rmcp::Error::invalid_params(
+                    format!("Project '{}' not found", project_name),
+                    None,
+                ));
+            };
+            let project = self.context.get_project(&project_path).await.unwrap();
+            let metadata = project
+                .cargo_remote
+                .metadata()
+                .await
+                .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
+            let content = serde_json::to_string_pretty(&workspace_summary(&metadata))
+                .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
+
+            Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri: request.uri,
+                    mime_type: Some("application/json".to_string()),
+                    text: content,
+                }],
+            })
+        } else if let Some(project_name) = request.uri.strip_prefix("lsp-stats://") {
+            let Some(project_path) = self.context.find_project_by_name(project_name).await else {
+                return Err(rmcp::Error::invalid_params(
+                    format!("Project '{}' not found", project_name),
+                    None,
+                ));
+            };
+            let project = self.context.get_project(&project_path).await.unwrap();
+            let content = serde_json::to_string_pretty(&project.lsp.request_timings())
+                .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
+
+            Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri: request.uri,
+                    mime_type: Some("application/json".to_string()),
+                    text: content,
+                }],
+            })
         } else {
             Err(rmcp::Error::invalid_params(
                 format!("Invalid resource URI: {}", request.uri),
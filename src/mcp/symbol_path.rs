@@ -0,0 +1,536 @@
+use std::fmt;
+use std::path::Path;
+
+use lsp_types::{SymbolInformation, SymbolKind};
+
+/// A Rust path naming a symbol, e.g. `Context`, `crate::context::Context`,
+/// `RustAnalyzerLsp::hover` or `<Foo as Bar>::baz`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolPath {
+    /// Segments before the name, generics stripped.
+    pub qualifier: Vec<String>,
+    /// The `<Type as Trait>` part of a qualified path.
+    pub qualified_self: Option<Owner>,
+    pub name: String,
+}
+
+/// The impl, trait or type an associated item belongs to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Owner {
+    pub self_ty: Option<String>,
+    pub trait_name: Option<String>,
+}
+
+/// Where a candidate symbol lives, reconstructed from its file and the document symbols around it.
+#[derive(Clone, Debug, Default)]
+pub struct CandidatePath {
+    pub crate_name: Option<String>,
+    /// File modules followed by inline `mod` blocks.
+    pub modules: Vec<String>,
+    pub owner: Option<Owner>,
+    pub name: String,
+}
+
+const STD_CRATES: [&str; 3] = ["std", "core", "alloc"];
+
+impl SymbolPath {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let path = path.trim().trim_end_matches("()");
+        let invalid = || format!("'{}' is not a valid Rust path.", path);
+
+        if let Some(rest) = path.strip_prefix('<') {
+            let close = matching_angle(rest).ok_or_else(invalid)?;
+            let (inner, tail) = (&rest[..close], &rest[close + 1..]);
+            let name = tail.strip_prefix("::").ok_or_else(invalid)?;
+            if name.is_empty() || name.contains("::") {
+                return Err(invalid());
+            }
+            let (self_ty, trait_name) = match split_top_level(inner, " as ") {
+                Some((self_ty, trait_name)) => (self_ty, Some(last_segment(trait_name))),
+                None => (inner, None),
+            };
+            return Ok(Self {
+                qualifier: Vec::new(),
+                qualified_self: Some(Owner {
+                    self_ty: Some(last_segment(self_ty)),
+                    trait_name,
+                }),
+                name: name.to_string(),
+            });
+        }
+
+        let mut segments: Vec<String> = strip_generics(path)
+            .split("::")
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+        let name = segments.pop().ok_or_else(invalid)?;
+        if segments
+            .iter()
+            .chain(std::iter::once(&name))
+            .any(|segment| !segment.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '!'))
+        {
+            return Err(invalid());
+        }
+        Ok(Self {
+            qualifier: segments,
+            qualified_self: None,
+            name: name.trim_end_matches('!').to_string(),
+        })
+    }
+
+    /// A plain name without any qualification.
+    pub fn is_bare(&self) -> bool {
+        self.qualifier.is_empty() && self.qualified_self.is_none()
+    }
+
+    /// Whether the path may start with a dependency's crate name, so the search has to include
+    /// libraries. Modules of the current crate can't be told apart by name alone.
+    pub fn may_be_external(&self, workspace_crates: &[String]) -> bool {
+        self.qualifier.first().is_some_and(|first| {
+            !["crate", "self", "super"].contains(&first.as_str())
+                && !workspace_crates.contains(first)
+                && first.starts_with(|c: char| c.is_lowercase())
+        })
+    }
+
+    /// How precisely `candidate` matches: `None` if it doesn't, otherwise the number of the
+    /// candidate's path segments the query left out (lower is more precise).
+    pub fn match_score(&self, candidate: &CandidatePath, workspace_crates: &[String]) -> Option<usize> {
+        if candidate.name != self.name {
+            return None;
+        }
+
+        if let Some(wanted) = &self.qualified_self {
+            let owner = candidate.owner.as_ref()?;
+            if wanted.trait_name.is_some() && owner.trait_name != wanted.trait_name {
+                return None;
+            }
+            return match (&owner.self_ty, &wanted.self_ty) {
+                (Some(self_ty), Some(wanted_ty)) if self_ty == wanted_ty => Some(0),
+                (Some(_), _) => None,
+                // A provided trait method, used when the impl doesn't override it.
+                (None, _) if wanted.trait_name.is_some() => Some(1),
+                (None, _) => None,
+            };
+        }
+
+        let mut qualifier: &[String] = &self.qualifier;
+        match qualifier.first().map(String::as_str) {
+            Some("crate") => {
+                let crate_name = candidate.crate_name.as_ref()?;
+                if !workspace_crates.is_empty() && !workspace_crates.contains(crate_name) {
+                    return None;
+                }
+                qualifier = &qualifier[1..];
+            }
+            Some(first) if candidate.crate_name.as_deref().is_some_and(|name| same_crate(name, first)) => {
+                qualifier = &qualifier[1..];
+            }
+            _ => {}
+        }
+        let qualifier: Vec<&String> = qualifier
+            .iter()
+            .filter(|segment| *segment != "self" && *segment != "super")
+            .collect();
+
+        // `Type::method` must name the method's own type or trait, not just any enclosing module.
+        if let (Some(owner), Some(last)) = (&candidate.owner, qualifier.last()) {
+            let names_owner = owner.self_ty.as_ref() == Some(*last) || owner.trait_name.as_ref() == Some(*last);
+            if !names_owner {
+                return None;
+            }
+        }
+
+        // The remaining segments have to appear in order, which lets re-exports such as
+        // `lsp::RustAnalyzerLsp` match `lsp::rust_analyzer_lsp::RustAnalyzerLsp`.
+        let segments = candidate.segments();
+        let mut remaining = segments.iter();
+        for wanted in &qualifier {
+            remaining.by_ref().find(|segment| segment == wanted)?;
+        }
+        Some(segments.len() - qualifier.len())
+    }
+}
+
+impl fmt::Display for SymbolPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(owner) = &self.qualified_self {
+            return write!(f, "{}::{}", owner, self.name);
+        }
+        for segment in &self.qualifier {
+            write!(f, "{}::", segment)?;
+        }
+        write!(f, "{}", self.name)
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.self_ty, &self.trait_name) {
+            (Some(self_ty), Some(trait_name)) => write!(f, "<{} as {}>", self_ty, trait_name),
+            (Some(self_ty), None) => write!(f, "{}", self_ty),
+            (None, Some(trait_name)) => write!(f, "{}", trait_name),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl CandidatePath {
+    /// Builds the path of `symbol` from its file location and the flat document symbols of
+    /// that file (`container_name` alone only names the immediate parent).
+    pub fn new(symbol: &SymbolInformation, file: &Path, document_symbols: &[SymbolInformation]) -> Self {
+        let (crate_name, mut modules) = file_module_path(file);
+        let position = symbol.location.range.start;
+        let mut enclosing: Vec<&SymbolInformation> = document_symbols
+            .iter()
+            .filter(|outer| {
+                let range = outer.location.range;
+                range.start <= position
+                    && position <= range.end
+                    && !(outer.name == symbol.name && outer.kind == symbol.kind)
+            })
+            .collect();
+        // Outermost first.
+        enclosing.sort_by_key(|outer| (outer.location.range.start, std::cmp::Reverse(outer.location.range.end)));
+
+        let mut owner = None;
+        for outer in enclosing {
+            match outer.kind {
+                SymbolKind::MODULE => modules.push(outer.name.clone()),
+                SymbolKind::OBJECT if outer.name.starts_with("impl") || outer.name.starts_with("unsafe impl") => {
+                    owner = Some(parse_impl_label(&outer.name));
+                }
+                SymbolKind::INTERFACE => {
+                    owner = Some(Owner {
+                        self_ty: None,
+                        trait_name: Some(outer.name.clone()),
+                    });
+                }
+                SymbolKind::STRUCT | SymbolKind::ENUM => {
+                    owner = Some(Owner {
+                        self_ty: Some(outer.name.clone()),
+                        trait_name: None,
+                    });
+                }
+                _ => {}
+            }
+        }
+        Self {
+            crate_name,
+            modules,
+            owner,
+            name: symbol.name.clone(),
+        }
+    }
+
+    fn segments(&self) -> Vec<String> {
+        let mut segments = self.modules.clone();
+        if let Some(owner) = &self.owner {
+            segments.extend(owner.self_ty.clone());
+            segments.extend(owner.trait_name.clone());
+        }
+        segments.push(self.name.clone());
+        segments
+    }
+}
+
+impl fmt::Display for CandidatePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut prefix: Vec<&str> = self.crate_name.iter().map(String::as_str).collect();
+        prefix.extend(self.modules.iter().map(String::as_str));
+        match &self.owner {
+            // `<T as Trait>::item` is only valid as the start of a path.
+            Some(owner @ Owner { self_ty: Some(_), trait_name: Some(_) }) => {
+                write!(f, "{}::{}", owner, self.name)?;
+                if !prefix.is_empty() {
+                    write!(f, " (in {})", prefix.join("::"))?;
+                }
+                Ok(())
+            }
+            owner => {
+                for segment in &prefix {
+                    write!(f, "{}::", segment)?;
+                }
+                if let Some(owner) = owner {
+                    write!(f, "{}::", owner)?;
+                }
+                write!(f, "{}", self.name)
+            }
+        }
+    }
+}
+
+/// Crate name and module path of a source file, derived from the nearest manifest and the
+/// file's position below that crate's `src` directory.
+fn file_module_path(file: &Path) -> (Option<String>, Vec<String>) {
+    let Some((crate_dir, crate_name)) = file.ancestors().skip(1).find_map(|dir| {
+        let manifest = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+        let manifest = manifest.parse::<toml::Table>().ok()?;
+        let name = manifest
+            .get("lib")
+            .and_then(|lib| lib.get("name"))
+            .or_else(|| manifest.get("package")?.get("name"))?
+            .as_str()?
+            .replace('-', "_");
+        Some((dir, name))
+    }) else {
+        return (None, Vec::new());
+    };
+
+    let Ok(relative) = file.strip_prefix(crate_dir.join("src")) else {
+        return (Some(crate_name), Vec::new());
+    };
+    let mut modules: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if modules.last().is_some_and(|last| ["lib", "main", "mod"].contains(&last.as_str())) {
+        modules.pop();
+    }
+    (Some(crate_name), modules)
+}
+
+/// Parses rust-analyzer's impl labels: `impl Foo`, `impl<T> Bar for Foo<T>`, `unsafe impl Send for Foo`.
+fn parse_impl_label(label: &str) -> Owner {
+    let label = strip_generics(label);
+    let label = label.trim_start_matches("unsafe ").trim_start_matches("impl").trim();
+    match label.split_once(" for ") {
+        Some((trait_name, self_ty)) => Owner {
+            self_ty: Some(last_segment(self_ty)),
+            trait_name: Some(last_segment(trait_name.trim_start_matches('!'))),
+        },
+        None => Owner {
+            self_ty: Some(last_segment(label)),
+            trait_name: None,
+        },
+    }
+}
+
+fn same_crate(a: &str, b: &str) -> bool {
+    a == b || (STD_CRATES.contains(&a) && STD_CRATES.contains(&b))
+}
+
+/// The last path segment of a type, without references, `dyn` or generics.
+fn last_segment(ty: &str) -> String {
+    let ty = strip_generics(ty);
+    let ty = ty
+        .trim()
+        .trim_start_matches('&')
+        .trim_start_matches("mut ")
+        .trim_start_matches("dyn ")
+        .trim();
+    ty.rsplit("::").next().unwrap_or(ty).trim().to_string()
+}
+
+fn strip_generics(text: &str) -> String {
+    let mut depth = 0usize;
+    text.chars()
+        .filter(|c| match c {
+            '<' => {
+                depth += 1;
+                false
+            }
+            '>' => {
+                depth = depth.saturating_sub(1);
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect()
+}
+
+/// Index of the `>` closing a `<` that was just consumed.
+fn matching_angle(text: &str) -> Option<usize> {
+    let mut depth = 1usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits at the first `separator` outside angle brackets.
+fn split_top_level<'a>(text: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && text[i..].starts_with(separator) => {
+                return Some((&text[..i], &text[i + separator.len()..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner(self_ty: Option<&str>, trait_name: Option<&str>) -> Owner {
+        Owner {
+            self_ty: self_ty.map(str::to_string),
+            trait_name: trait_name.map(str::to_string),
+        }
+    }
+
+    fn candidate(crate_name: &str, modules: &[&str], owner: Option<Owner>, name: &str) -> CandidatePath {
+        CandidatePath {
+            crate_name: Some(crate_name.to_string()),
+            modules: modules.iter().map(|module| module.to_string()).collect(),
+            owner,
+            name: name.to_string(),
+        }
+    }
+
+    fn score(path: &str, candidate: &CandidatePath) -> Option<usize> {
+        let workspace_crates = ["rust_devtools_mcp".to_string()];
+        SymbolPath::parse(path).unwrap().match_score(candidate, &workspace_crates)
+    }
+
+    #[test]
+    fn parses_plain_paths() {
+        let path = SymbolPath::parse("Context").unwrap();
+        assert!(path.is_bare());
+        assert_eq!(path.name, "Context");
+
+        let path = SymbolPath::parse("crate::context::Context").unwrap();
+        assert_eq!(path.qualifier, ["crate", "context"]);
+        assert_eq!(path.name, "Context");
+
+        let path = SymbolPath::parse("RustAnalyzerLsp::hover()").unwrap();
+        assert_eq!(path.qualifier, ["RustAnalyzerLsp"]);
+        assert_eq!(path.name, "hover");
+
+        let path = SymbolPath::parse("std::vec::Vec::<u8>::push").unwrap();
+        assert_eq!(path.qualifier, ["std", "vec", "Vec"]);
+        assert_eq!(path.name, "push");
+
+        let path = SymbolPath::parse("tokio::select!").unwrap();
+        assert_eq!(path.qualifier, ["tokio"]);
+        assert_eq!(path.name, "select");
+    }
+
+    #[test]
+    fn parses_qualified_self_paths() {
+        let path = SymbolPath::parse("<Foo as Bar>::baz").unwrap();
+        assert!(path.qualifier.is_empty());
+        assert_eq!(path.qualified_self, Some(owner(Some("Foo"), Some("Bar"))));
+        assert_eq!(path.name, "baz");
+        assert_eq!(path.to_string(), "<Foo as Bar>::baz");
+
+        let path = SymbolPath::parse("<Vec<T> as std::iter::IntoIterator>::into_iter").unwrap();
+        assert_eq!(path.qualified_self, Some(owner(Some("Vec"), Some("IntoIterator"))));
+        assert_eq!(path.name, "into_iter");
+    }
+
+    #[test]
+    fn rejects_invalid_paths() {
+        for path in ["", "::", "foo bar", "<Foo as Bar>", "<Foo as Bar>::baz::qux", "foo::bar-baz"] {
+            assert!(SymbolPath::parse(path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn tells_external_paths_apart() {
+        let workspace_crates = ["rust_devtools_mcp".to_string()];
+        let external = |path: &str| SymbolPath::parse(path).unwrap().may_be_external(&workspace_crates);
+        assert!(external("tokio::spawn"));
+        assert!(!external("crate::context::Context"));
+        assert!(!external("rust_devtools_mcp::context::Context"));
+        assert!(!external("RustAnalyzerLsp::hover"));
+        assert!(!external("Context"));
+    }
+
+    #[test]
+    fn more_specific_paths_score_lower() {
+        let context = candidate("rust_devtools_mcp", &["context"], None, "Context");
+        assert_eq!(score("Context", &context), Some(2));
+        assert_eq!(score("context::Context", &context), Some(1));
+        assert_eq!(score("crate::context::Context", &context), Some(1));
+        assert_eq!(score("rust_devtools_mcp::context::Context", &context), Some(1));
+        assert_eq!(score("crate::project::Context", &context), None);
+        assert_eq!(score("Project", &context), None);
+    }
+
+    #[test]
+    fn crate_paths_only_match_the_workspace() {
+        let dependency = candidate("tokio", &["task"], None, "spawn");
+        assert_eq!(score("crate::task::spawn", &dependency), None);
+        assert_eq!(score("tokio::spawn", &dependency), Some(2));
+        assert_eq!(score("tokio::task::spawn", &dependency), Some(1));
+
+        let local = candidate("rust_devtools_mcp", &[], None, "spawn");
+        assert_eq!(score("tokio::spawn", &local), None);
+    }
+
+    #[test]
+    fn module_paths_may_skip_private_modules() {
+        let lsp = candidate("rust_devtools_mcp", &["lsp", "rust_analyzer_lsp"], None, "RustAnalyzerLsp");
+        assert_eq!(score("lsp::RustAnalyzerLsp", &lsp), Some(2));
+        assert_eq!(score("crate::lsp::rust_analyzer_lsp::RustAnalyzerLsp", &lsp), Some(1));
+        assert_eq!(score("rust_analyzer_lsp::lsp::RustAnalyzerLsp", &lsp), None);
+    }
+
+    #[test]
+    fn std_paths_match_core_and_alloc() {
+        let push = candidate("alloc", &["vec"], Some(owner(Some("Vec"), None)), "push");
+        assert_eq!(score("std::vec::Vec::push", &push), Some(1));
+        assert_eq!(score("Vec::push", &push), Some(2));
+    }
+
+    #[test]
+    fn methods_must_name_their_owner() {
+        let hover = candidate(
+            "rust_devtools_mcp",
+            &["lsp", "rust_analyzer_lsp"],
+            Some(owner(Some("RustAnalyzerLsp"), None)),
+            "hover",
+        );
+        assert_eq!(score("RustAnalyzerLsp::hover", &hover), Some(3));
+        assert_eq!(score("rust_analyzer_lsp::hover", &hover), None);
+        assert_eq!(score("Context::hover", &hover), None);
+
+        let trait_method = candidate("rust_devtools_mcp", &["lsp"], Some(owner(None, Some("Notifier"))), "notify");
+        assert_eq!(score("Notifier::notify", &trait_method), Some(2));
+    }
+
+    #[test]
+    fn qualified_self_paths_match_impls() {
+        let implemented = candidate("rust_devtools_mcp", &[], Some(owner(Some("Foo"), Some("Bar"))), "baz");
+        let inherent = candidate("rust_devtools_mcp", &[], Some(owner(Some("Foo"), None)), "baz");
+        let provided = candidate("rust_devtools_mcp", &[], Some(owner(None, Some("Bar"))), "baz");
+        assert_eq!(score("<Foo as Bar>::baz", &implemented), Some(0));
+        assert_eq!(score("<Foo as Bar>::baz", &inherent), None);
+        assert_eq!(score("<Foo as Bar>::baz", &provided), Some(1));
+        assert_eq!(score("<Qux as Bar>::baz", &implemented), None);
+        assert_eq!(score("<Foo>::baz", &inherent), Some(0));
+    }
+
+    #[test]
+    fn parses_impl_labels() {
+        assert_eq!(parse_impl_label("impl Foo"), owner(Some("Foo"), None));
+        assert_eq!(parse_impl_label("impl<T> Foo<T>"), owner(Some("Foo"), None));
+        assert_eq!(parse_impl_label("impl<T> Bar for Foo<T>"), owner(Some("Foo"), Some("Bar")));
+        assert_eq!(parse_impl_label("impl fmt::Display for Wrapper"), owner(Some("Wrapper"), Some("Display")));
+        assert_eq!(parse_impl_label("unsafe impl Send for Foo"), owner(Some("Foo"), Some("Send")));
+        assert_eq!(parse_impl_label("impl !Sync for Foo"), owner(Some("Foo"), Some("Sync")));
+        assert_eq!(
+            parse_impl_label("impl<'a> Iterator for std::slice::Iter<'a, u8>"),
+            owner(Some("Iter"), Some("Iterator"))
+        );
+    }
+}
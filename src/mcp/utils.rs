@@ -4,7 +4,9 @@ use std::sync::Arc;
 use std::collections::HashMap;
 use rayon::prelude::*;
 
+use super::symbol_path::{CandidatePath, SymbolPath};
 use crate::context::ProjectContext;
+use crate::lsp::Overlay;
 use anyhow::Result;
 use lsp_types::{Position, TextEdit, WorkspaceEdit};
 use rmcp::model::{CallToolResult, Content};
@@ -13,15 +15,227 @@ pub fn error_response(message: &str) -> CallToolResult {
     CallToolResult::error(vec![Content::text(message.to_string())])
 }
 
-/// Resolves a symbol name within a project, handling ambiguity.
+/// Where a symbol search looks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymbolScope {
+    /// Workspace members only (rust-analyzer's default).
+    #[default]
+    Workspace,
+    /// Workspace members, dependencies and the standard library.
+    WithDependencies,
+}
+
+/// Scope and kind filters for `workspace/symbol`.
+/// rust-analyzer only searches types in the workspace unless the query carries its `#` (all
+/// symbols) or `*` (include libraries) modifiers, so the filters are expressed through those.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolQuery {
+    pub scope: SymbolScope,
+    /// Empty means any kind.
+    pub kinds: Vec<lsp_types::SymbolKind>,
+}
+
+impl SymbolQuery {
+    pub fn new(scope: Option<&str>, kinds: Option<&[String]>) -> Result<Self, String> {
+        let scope = match scope {
+            None | Some("workspace") => SymbolScope::Workspace,
+            Some("dependencies") => SymbolScope::WithDependencies,
+            Some(other) => {
+                return Err(format!(
+                    "Unknown scope '{}'. Use 'workspace' or 'dependencies'.",
+                    other
+                ));
+            }
+        };
+        let kinds = kinds
+            .unwrap_or_default()
+            .iter()
+            .map(|kind| parse_symbol_kind(kind))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { scope, kinds })
+    }
+
+    fn query_string(&self, symbol_name: &str, all_symbols: bool) -> String {
+        let mut query = symbol_name.to_string();
+        // Only a filter made of type kinds can do without `#`; `matches` drops the rest.
+        if all_symbols
+            || self.kinds.is_empty()
+            || self.kinds.iter().any(|kind| !is_type_kind(*kind))
+        {
+            query.push('#');
+        }
+        if self.scope == SymbolScope::WithDependencies {
+            query.push('*');
+        }
+        query
+    }
+
+    fn matches(&self, symbol: &lsp_types::SymbolInformation) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&symbol.kind)
+    }
+}
+
+/// Maps a user-facing kind name onto the `SymbolKind` rust-analyzer reports for it.
+fn parse_symbol_kind(kind: &str) -> Result<lsp_types::SymbolKind, String> {
+    use lsp_types::SymbolKind;
+    Ok(match kind {
+        "function" | "macro" => SymbolKind::FUNCTION,
+        "method" => SymbolKind::METHOD,
+        "struct" | "union" => SymbolKind::STRUCT,
+        "enum" => SymbolKind::ENUM,
+        "variant" => SymbolKind::ENUM_MEMBER,
+        "trait" => SymbolKind::INTERFACE,
+        "type_alias" => SymbolKind::TYPE_PARAMETER,
+        "module" => SymbolKind::MODULE,
+        "constant" => SymbolKind::CONSTANT,
+        "static" => SymbolKind::VARIABLE,
+        "field" => SymbolKind::FIELD,
+        other => {
+            return Err(format!(
+                "Unknown symbol kind '{}'. Use function, method, macro, struct, union, enum, variant, trait, type_alias, module, constant, static or field.",
+                other
+            ));
+        }
+    })
+}
+
+fn is_type_kind(kind: lsp_types::SymbolKind) -> bool {
+    use lsp_types::SymbolKind;
+    [
+        SymbolKind::STRUCT,
+        SymbolKind::ENUM,
+        SymbolKind::INTERFACE,
+        SymbolKind::TYPE_PARAMETER,
+    ]
+    .contains(&kind)
+}
+
+/// Classifies the file a symbol lives in: workspace code, a registry or git dependency, or
+/// the standard library sources shipped with the toolchain.
+pub fn symbol_origin(path: &Path, project_root: &Path) -> serde_json::Value {
+    if path.starts_with(project_root) {
+        return serde_json::json!({ "source": "workspace" });
+    }
+    let components: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let after = |marker: &[&str]| {
+        components
+            .windows(marker.len())
+            .position(|window| window.iter().zip(marker).all(|(a, b)| a == b))
+            .and_then(|i| components.get(i + marker.len()..))
+    };
+
+    // ~/.cargo/registry/src/<index>/<name>-<version>/...
+    if let Some([_, crate_dir, ..]) = after(&["registry", "src"]) {
+        let split = crate_dir
+            .char_indices()
+            .find(|(i, c)| *c == '-' && crate_dir[i + 1..].starts_with(|c: char| c.is_ascii_digit()))
+            .map(|(i, _)| i);
+        if let Some(i) = split {
+            return serde_json::json!({
+                "source": "dependency",
+                "crate": &crate_dir[..i],
+                "version": &crate_dir[i + 1..],
+            });
+        }
+    }
+    // <sysroot>/lib/rustlib/src/rust/library/<crate>/...
+    if let Some([library_crate, ..]) = after(&["rustlib", "src", "rust", "library"]) {
+        return serde_json::json!({ "source": "std", "crate": library_crate });
+    }
+    if after(&["git", "checkouts"]).is_some() {
+        return serde_json::json!({ "source": "git_dependency" });
+    }
+    serde_json::json!({ "source": "external" })
+}
+
+/// Resolves a symbol within a project, handling ambiguity. `symbol_name` is either a bare name or
+/// a Rust path (`crate::context::Context`, `RustAnalyzerLsp::hover`, `<Foo as Bar>::baz`), which
+/// is matched against the module, impl and trait each candidate is declared in.
 pub async fn resolve_symbol_in_project(
     project: &Arc<ProjectContext>,
     symbol_name: &str,
-    file_hint: Option<&str>,
+    query: &SymbolQuery,
 ) -> Result<lsp_types::SymbolInformation, String> {
+    let path = SymbolPath::parse(symbol_name)?;
+    let workspace_crates = workspace_crate_names(project).await;
+
+    let mut query = query.clone();
+    if path.may_be_external(&workspace_crates) {
+        query.scope = SymbolScope::WithDependencies;
+    }
+    let mut symbols = search_symbols(project, &path, &query).await?;
+    // A qualified path already says which item is meant, so look beyond the workspace before
+    // giving up.
+    if symbols.is_empty() && !path.is_bare() && query.scope == SymbolScope::Workspace {
+        query.scope = SymbolScope::WithDependencies;
+        symbols = search_symbols(project, &path, &query).await?;
+    }
+
+    if symbols.is_empty() {
+        let hint = if query.scope == SymbolScope::Workspace {
+            " For items from dependencies or std, search with scope 'dependencies'."
+        } else {
+            ""
+        };
+        return Err(format!("Symbol '{}' not found in project.{}", symbol_name, hint));
+    }
+
+    if path.is_bare() {
+        if symbols.len() == 1 {
+            return Ok(symbols.into_iter().next().unwrap());
+        }
+        // Try to deduplicate symbols that are essentially the same type
+        let deduplicated_symbols = deduplicate_symbols(&symbols);
+        if deduplicated_symbols.len() == 1 {
+            return Ok(deduplicated_symbols.into_iter().next().unwrap());
+        }
+        // Still ambiguous, return a list for the LLM to handle.
+        let candidates = candidate_paths(project, deduplicated_symbols).await;
+        return Err(format!(
+            "Symbol '{}' is ambiguous. Use a qualified path such as `module::Type::method` or ask the user to clarify from the following candidates:\n{}",
+            symbol_name,
+            format_candidates(&candidates)
+        ));
+    }
+
+    let candidates = candidate_paths(project, symbols).await;
+    let mut scored: Vec<(usize, &(lsp_types::SymbolInformation, CandidatePath))> = candidates
+        .iter()
+        .filter_map(|candidate| Some((path.match_score(&candidate.1, &workspace_crates)?, candidate)))
+        .collect();
+    let Some(best) = scored.iter().map(|(score, _)| *score).min() else {
+        return Err(format!(
+            "No symbol matches path '{}'. Items named '{}':\n{}",
+            path,
+            path.name,
+            format_candidates(&candidates)
+        ));
+    };
+    scored.retain(|(score, _)| *score == best);
+    if let [(_, (symbol, _))] = scored.as_slice() {
+        return Ok(symbol.clone());
+    }
+    let matches: Vec<_> = scored.into_iter().map(|(_, candidate)| candidate.clone()).collect();
+    Err(format!(
+        "Path '{}' is ambiguous. Qualify it further or ask the user to clarify from the following candidates:\n{}",
+        path,
+        format_candidates(&matches)
+    ))
+}
+
+/// Runs `workspace/symbol` for the last segment of `path` and applies the kind filter.
+/// Qualified paths only accept exact name matches, so they also search non-type items.
+async fn search_symbols(
+    project: &Arc<ProjectContext>,
+    path: &SymbolPath,
+    query: &SymbolQuery,
+) -> Result<Vec<lsp_types::SymbolInformation>, String> {
     let workspace_response = project
         .lsp
-        .workspace_symbols(symbol_name.to_string())
+        .workspace_symbols(query.query_string(&path.name, !path.is_bare()))
         .await
         .map_err(|e| format!("LSP error while searching for symbol: {}", e))?
         .unwrap_or(lsp_types::WorkspaceSymbolResponse::Flat(vec![]));
@@ -50,55 +264,282 @@ pub async fn resolve_symbol_in_project(
                 .collect()
         }
     };
+    Ok(symbols
+        .into_iter()
+        .filter(|symbol| query.matches(symbol) && (path.is_bare() || symbol.name == path.name))
+        .collect())
+}
 
-    if symbols.is_empty() {
-        return Err(format!("Symbol '{}' not found in project.", symbol_name));
+/// Upper bound on the files whose document symbols are fetched to build candidate paths.
+const MAX_PATH_LOOKUP_FILES: usize = 30;
+
+/// Pairs each symbol with its full path. Symbols in files past the lookup limit are dropped.
+async fn candidate_paths(
+    project: &Arc<ProjectContext>,
+    symbols: Vec<lsp_types::SymbolInformation>,
+) -> Vec<(lsp_types::SymbolInformation, CandidatePath)> {
+    let mut document_symbols: HashMap<PathBuf, Vec<lsp_types::SymbolInformation>> = HashMap::new();
+    let mut candidates = Vec::new();
+    for symbol in symbols {
+        let Ok(file) = symbol.location.uri.to_file_path() else {
+            continue;
+        };
+        if !document_symbols.contains_key(&file) {
+            if document_symbols.len() >= MAX_PATH_LOOKUP_FILES {
+                continue;
+            }
+            let symbols = project.lsp.document_symbols(&file).await.unwrap_or_default();
+            document_symbols.insert(file.clone(), symbols);
+        }
+        let path = CandidatePath::new(&symbol, &file, &document_symbols[&file]);
+        candidates.push((symbol, path));
     }
+    candidates
+}
 
-    if symbols.len() == 1 {
-        return Ok(symbols.into_iter().next().unwrap());
+fn format_candidates(candidates: &[(lsp_types::SymbolInformation, CandidatePath)]) -> String {
+    candidates
+        .iter()
+        .filter_map(|(symbol, path)| {
+            symbol.location.uri.to_file_path().ok().map(|file| {
+                format!(
+                    "- `{}` (kind: {:?}) in `{}:{}`",
+                    path,
+                    symbol.kind,
+                    file.display(),
+                    symbol.location.range.start.line + 1
+                )
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Crate names of the workspace members as they appear in paths (`[lib] name`, dashes as underscores).
+async fn workspace_crate_names(project: &Arc<ProjectContext>) -> Vec<String> {
+    let Ok(metadata) = project.cargo_remote.metadata().await else {
+        return Vec::new();
+    };
+    metadata
+        .workspace_packages()
+        .map(|package| {
+            package
+                .targets
+                .iter()
+                .find(|target| target.kind.iter().any(|kind| kind == "lib" || kind == "proc-macro"))
+                .map_or(package.name.as_str(), |target| target.name.as_str())
+                .replace('-', "_")
+        })
+        .collect()
+}
+
+/// What a symbol tool operates on: a symbol found by name or path, or whatever sits at a
+/// position in a file. Positions reach things a name can't, like locals and closure parameters.
+pub enum SymbolTarget {
+    Symbol(lsp_types::SymbolInformation),
+    Position {
+        file: PathBuf,
+        position: Position,
+        /// The identifier under the position, empty if there is none.
+        identifier: String,
+        #[allow(dead_code)] // Keep the unsaved contents visible for as long as the target is used
+        overlay: Option<Overlay>,
+    },
+}
+
+impl SymbolTarget {
+    pub fn file(&self) -> Result<PathBuf, String> {
+        match self {
+            SymbolTarget::Symbol(symbol) => symbol
+                .location
+                .uri
+                .to_file_path()
+                .map_err(|_| format!("Invalid file path for symbol '{}'", symbol.name)),
+            SymbolTarget::Position { file, .. } => Ok(file.clone()),
+        }
     }
 
-    // Try to deduplicate symbols that are essentially the same type
-    let deduplicated_symbols = deduplicate_symbols(&symbols);
-    
-    if deduplicated_symbols.len() == 1 {
-        return Ok(deduplicated_symbols.into_iter().next().unwrap());
+    pub fn position(&self) -> Position {
+        match self {
+            SymbolTarget::Symbol(symbol) => symbol.location.range.start,
+            SymbolTarget::Position { position, .. } => *position,
+        }
     }
 
-    // More than one match, try to use file_hint to disambiguate.
-    if let Some(hint) = file_hint {
-        let hint_path = Path::new(hint);
-        for symbol in &deduplicated_symbols {
-            if let Ok(symbol_path) = symbol.location.uri.to_file_path() {
-                if symbol_path.ends_with(hint_path) || symbol_path.to_string_lossy().contains(hint)
-                {
-                    return Ok(symbol.clone());
-                }
-            }
+    pub fn name(&self) -> &str {
+        match self {
+            SymbolTarget::Symbol(symbol) => &symbol.name,
+            SymbolTarget::Position { identifier, .. } => identifier,
         }
     }
+}
 
-    // Still ambiguous, return a list for the LLM to handle.
-    let candidates = deduplicated_symbols
-        .iter()
-        .filter_map(|s| {
-            s.location.uri.to_file_path().ok().and_then(|path| {
-                Some(format!(
-                    "- `{}` (kind: {:?}) in `{}`",
-                    s.name,
-                    s.kind,
-                    path.display()
-                ))
+/// Where a symbol tool should look: `symbol_name`, or `file` + `line` + `column`/`text`.
+#[derive(Clone, Debug, Default)]
+pub struct TargetArgs {
+    pub symbol_name: Option<String>,
+    pub file: Option<String>,
+    /// 1-based.
+    pub line: Option<u32>,
+    /// 1-based, in characters.
+    pub column: Option<u32>,
+    /// A snippet on `line` whose first occurrence marks the column.
+    pub text: Option<String>,
+    /// Unsaved contents of `file`, analysed instead of the file on disk.
+    pub content: Option<String>,
+}
+
+pub async fn resolve_symbol_target(
+    project: &Arc<ProjectContext>,
+    args: &TargetArgs,
+    query: &SymbolQuery,
+) -> Result<SymbolTarget, String> {
+    match (&args.symbol_name, &args.file, args.line) {
+        (Some(_), Some(_), _) => Err("Provide either symbol_name or file and line, not both.".to_string()),
+        (Some(_), None, _) if args.content.is_some() => {
+            Err("'content' can only be used with file and line.".to_string())
+        }
+        (Some(symbol_name), None, _) => resolve_symbol_in_project(project, symbol_name, query)
+            .await
+            .map(SymbolTarget::Symbol),
+        (None, Some(file), Some(line)) => {
+            let file = resolve_file_path(project, file).await?;
+            let content = match &args.content {
+                Some(content) => content.clone(),
+                None => fs::read_to_string(&file)
+                    .map_err(|e| format!("Failed to read '{}': {}", file.display(), e))?,
+            };
+            let (position, identifier) =
+                locate_in_text(&content, line, args.column, args.text.as_deref())?;
+            let overlay = open_overlay(project, &file, args.content.clone()).await?;
+            Ok(SymbolTarget::Position {
+                file,
+                position,
+                identifier,
+                overlay,
             })
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+        }
+        (None, Some(_), None) => Err("'line' is required together with 'file'.".to_string()),
+        (None, None, _) => Err("Provide symbol_name, or file and line with column or text.".to_string()),
+    }
+}
 
-    Err(format!(
-        "Symbol '{}' is ambiguous. Please provide a more specific file_hint or ask the user to clarify from the following candidates:\n{}",
-        symbol_name, candidates
-    ))
+/// Turns a 1-based line and a 1-based character column (or a snippet on that line) into an LSP
+/// position, which counts UTF-16 code units, and returns the identifier found there.
+fn locate_in_text(
+    content: &str,
+    line: u32,
+    column: Option<u32>,
+    text: Option<&str>,
+) -> Result<(Position, String), String> {
+    let Some(line_text) = line.checked_sub(1).and_then(|i| content.lines().nth(i as usize)) else {
+        return Err(format!("Line {} is out of range ({} lines).", line, content.lines().count()));
+    };
+    let chars: Vec<char> = line_text.chars().collect();
+
+    let char_index = match (column, text) {
+        (Some(column), _) => {
+            let index = column.saturating_sub(1) as usize;
+            if index > chars.len() {
+                return Err(format!(
+                    "Column {} is past the end of line {} ({} characters).",
+                    column,
+                    line,
+                    chars.len()
+                ));
+            }
+            index
+        }
+        (None, Some(text)) => {
+            let byte_index = line_text.find(text).ok_or_else(|| {
+                format!("'{}' does not occur on line {}: `{}`", text, line, line_text.trim())
+            })?;
+            line_text[..byte_index].chars().count()
+        }
+        (None, None) => return Err("Provide 'column' or a 'text' snippet to locate the symbol on the line.".to_string()),
+    };
+
+    let is_ident = |c: &char| c.is_alphanumeric() || *c == '_';
+    let start = chars[..char_index]
+        .iter()
+        .rposition(|c| !is_ident(c))
+        .map_or(0, |i| i + 1);
+    let end = chars[char_index..]
+        .iter()
+        .position(|c| !is_ident(c))
+        .map_or(chars.len(), |i| char_index + i);
+    let identifier: String = chars[start..end].iter().collect();
+
+    let character = chars[..char_index].iter().map(|c| c.len_utf16() as u32).sum();
+    Ok((Position::new(line - 1, character), identifier))
+}
+
+/// Resolves `file` and the cursor position in it, measured against `content` when the caller
+/// passes unsaved contents and against the file on disk otherwise.
+pub async fn resolve_cursor(
+    project: &Arc<ProjectContext>,
+    file: &str,
+    line: u32,
+    column: Option<u32>,
+    content: Option<&str>,
+) -> Result<(PathBuf, Position), String> {
+    let file_path = resolve_file_path(project, file).await?;
+    let position = match content {
+        Some(content) => cursor_position(content, line, column)?,
+        None => {
+            let content = fs::read_to_string(&file_path)
+                .map_err(|e| format!("Failed to read '{}': {}", file_path.display(), e))?;
+            cursor_position(&content, line, column)?
+        }
+    };
+    Ok((file_path, position))
+}
+
+/// Shows rust-analyzer `content` in place of the file on disk for as long as the returned
+/// overlay is held. Tools keep it until they return.
+pub async fn open_overlay(
+    project: &Arc<ProjectContext>,
+    file_path: &Path,
+    content: Option<String>,
+) -> Result<Option<Overlay>, String> {
+    let Some(content) = content else {
+        return Ok(None);
+    };
+    open_overlay_with(project, file_path, content).await.map(Some)
+}
+
+/// [`open_overlay`] for tools that always take contents.
+pub async fn open_overlay_with(
+    project: &Arc<ProjectContext>,
+    file_path: &Path,
+    content: String,
+) -> Result<Overlay, String> {
+    project
+        .lsp
+        .overlay(file_path, content)
+        .await
+        .map_err(|e| format!("Failed to send unsaved contents to rust-analyzer: {}", e))
+}
+
+/// LSP position of a 1-based line and 1-based character column in `content`. Without a column
+/// the position is the end of the line, where an agent typing code would have its cursor.
+fn cursor_position(content: &str, line: u32, column: Option<u32>) -> Result<Position, String> {
+    let Some(line_text) = line.checked_sub(1).and_then(|i| content.lines().nth(i as usize)) else {
+        return Err(format!("Line {} is out of range ({} lines).", line, content.lines().count()));
+    };
+    let chars = line_text.chars().count();
+    let index = match column {
+        Some(column) if column as usize > chars + 1 => {
+            return Err(format!(
+                "Column {} is past the end of line {} ({} characters).",
+                column, line, chars
+            ));
+        }
+        Some(column) => column.saturating_sub(1) as usize,
+        None => chars,
+    };
+    let character = line_text.chars().take(index).map(|c| c.len_utf16() as u32).sum();
+    Ok(Position::new(line - 1, character))
 }
 
 /// Returns the lines between start_line and end_line (inclusive) from the given file path
@@ -147,6 +588,14 @@ pub fn get_file_lines(
     }
 }
 
+/// Like [`get_file_lines`], for text that isn't (or isn't yet) on disk.
+pub fn text_lines(content: &str, start_line: u32, end_line: u32, prefix: u8, suffix: u8) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = start_line.saturating_sub(prefix as u32) as usize;
+    let end = (end_line.saturating_add(suffix as u32) as usize).min(lines.len().checked_sub(1)?);
+    (start <= end).then(|| lines[start..=end].join("\n"))
+}
+
 /// Deduplicates symbols that are essentially the same type
 /// Prioritizes symbols based on file type and location preferences
 fn deduplicate_symbols(symbols: &[lsp_types::SymbolInformation]) -> Vec<lsp_types::SymbolInformation> {
@@ -281,14 +730,15 @@ fn calculate_symbol_score(symbol: &lsp_types::SymbolInformation) -> i32 {
     score
 }
 
-/// Applies a `WorkspaceEdit` to the file system.
+/// Applies a `WorkspaceEdit` to the file system and returns the files it touched.
 /// This function is critical for any code modification tools.
-pub fn apply_workspace_edit(edit: &WorkspaceEdit) -> std::result::Result<(), String> {
+pub fn apply_workspace_edit(edit: &WorkspaceEdit) -> std::result::Result<Vec<PathBuf>, String> {
     let Some(changes) = &edit.changes else {
         // TODO: Handle documentChanges field as well for more complex edits
-        return Ok(());
+        return Ok(Vec::new());
     };
 
+    let mut touched_files = Vec::new();
     for (uri, text_edits) in changes {
         let path = uri
             .to_file_path()
@@ -296,9 +746,10 @@ pub fn apply_workspace_edit(edit: &WorkspaceEdit) -> std::result::Result<(), Str
 
         apply_edits_to_file(&path, text_edits)
             .map_err(|e| format!("Failed to apply edits to {}: {}", path.display(), e))?;
+        touched_files.push(path);
     }
 
-    Ok(())
+    Ok(touched_files)
 }
 
 /// Helper function to apply a series of `TextEdit`s to a single file.
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use url::Url;
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
@@ -10,10 +12,113 @@ pub enum TransportType {
     StreamableHttp { host: String, port: u16 },
 }
 
+/// Timeout applied to tools that have no entry in `ProjectSettings::timeouts`.
+const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 600;
+
+/// Target directory used by cargo tools, relative to the project root unless absolute.
+/// Kept apart from `target` so our runs don't block on or invalidate rust-analyzer's flycheck.
+const DEFAULT_CARGO_TARGET_DIR: &str = "target/mcp";
+
+/// How cargo and rustfmt are invoked for a project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CargoSettings {
+    /// `CARGO_TARGET_DIR` for cargo tools.
+    pub target_dir: PathBuf,
+    /// Toolchain to run with (e.g. `nightly`), passed as `RUSTUP_TOOLCHAIN`.
+    pub toolchain: Option<String>,
+    /// Extra flags appended to `RUSTFLAGS`.
+    pub rustflags: Vec<String>,
+    /// Additional environment variables.
+    pub env: HashMap<String, String>,
+}
+
+impl Default for CargoSettings {
+    fn default() -> Self {
+        Self {
+            target_dir: PathBuf::from(DEFAULT_CARGO_TARGET_DIR),
+            toolchain: None,
+            rustflags: vec![],
+            env: HashMap::new(),
+        }
+    }
+}
+
+impl CargoSettings {
+    /// Environment for a cargo or rustfmt process started in `root`, sorted by name.
+    pub fn env(&self, root: &Path) -> Vec<(String, String)> {
+        let mut env: BTreeMap<String, String> = self
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        env.insert(
+            "CARGO_TARGET_DIR".to_string(),
+            root.join(&self.target_dir).to_string_lossy().into_owned(),
+        );
+        if let Some(toolchain) = &self.toolchain {
+            let toolchain = toolchain.strip_prefix('+').unwrap_or(toolchain);
+            env.insert("RUSTUP_TOOLCHAIN".to_string(), toolchain.to_string());
+        }
+        if !self.rustflags.is_empty() {
+            let base = env
+                .get("RUSTFLAGS")
+                .cloned()
+                .or_else(|| std::env::var("RUSTFLAGS").ok())
+                .unwrap_or_default();
+            let rustflags = std::iter::once(base.as_str())
+                .chain(self.rustflags.iter().map(String::as_str))
+                .filter(|flag| !flag.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            env.insert("RUSTFLAGS".to_string(), rustflags);
+        }
+        env.into_iter().collect()
+    }
+}
+
+/// Per-project tool settings, stored next to the project entry in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    /// Run rustfmt on every file touched by an applied workspace edit.
+    pub format_on_edit: bool,
+    /// Timeout in seconds for tools without an entry in `timeouts`.
+    pub default_timeout: u64,
+    /// Per-tool timeouts in seconds, keyed by tool name (e.g. `test_project = 1800`).
+    pub timeouts: HashMap<String, u64>,
+    /// Target directory, toolchain and environment for cargo tools.
+    pub cargo: CargoSettings,
+}
+
+impl Default for ProjectSettings {
+    fn default() -> Self {
+        Self {
+            format_on_edit: false,
+            default_timeout: DEFAULT_TOOL_TIMEOUT_SECS,
+            timeouts: HashMap::new(),
+            cargo: CargoSettings::default(),
+        }
+    }
+}
+
+impl ProjectSettings {
+    pub fn tool_timeout(&self, tool: &str) -> Duration {
+        let secs = self
+            .timeouts
+            .get(tool)
+            .copied()
+            .unwrap_or(self.default_timeout);
+        Duration::from_secs(secs)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
     pub root: PathBuf,
     pub ignore_crates: Vec<String>,
+    #[serde(flatten)]
+    pub settings: ProjectSettings,
 }
 
 impl Project {
@@ -22,6 +127,7 @@ impl Project {
         Ok(Self {
             root,
             ignore_crates: vec![],
+            settings: ProjectSettings::default(),
         })
     }
 
@@ -29,6 +135,10 @@ impl Project {
         &self.ignore_crates
     }
 
+    pub fn settings(&self) -> &ProjectSettings {
+        &self.settings
+    }
+
     pub fn root(&self) -> &PathBuf {
         &self.root
     }
@@ -37,9 +147,4 @@ impl Project {
         Url::from_file_path(&self.root)
             .map_err(|_| anyhow::anyhow!("Failed to create project root URI"))
     }
-
-    pub fn file_uri(&self, relative_path: impl AsRef<Path>) -> Result<Url> {
-        Url::from_file_path(self.root.join(relative_path))
-            .map_err(|_| anyhow::anyhow!("Failed to create file URI"))
-    }
 }